@@ -0,0 +1,424 @@
+use crate::engine::physics::{QuadTree, Rectangle};
+use crate::space::objects::{CelestialObject, Position};
+
+use nalgebra::{Point2, Vector2};
+
+/// Debris are placed this much further apart than touching, so rounding cannot make them overlap
+/// and merge back on the next step.
+pub const CLEARANCE: f64 = 1.01;
+
+/// What happens to two bodies once they touch.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Response {
+    /// Perfectly inelastic merge into a single body.
+    #[default]
+    Merge,
+    /// Impulse along the contact normal; `restitution` 1 is elastic, 0 kills the approach speed.
+    Bounce { restitution: f64 },
+    /// The lighter body shatters into `pieces` debris, the heavier one survives.
+    Fragment { pieces: usize },
+    /// The heavier body swallows the lighter one and keeps its name and kind. Always used, whatever
+    /// the policy, once a body's center crosses a black hole's event horizon, and for test particles
+    /// hitting a massive body.
+    Absorb,
+}
+
+/// Chooses a `Response` for every colliding pair: a default plus overrides keyed by body names.
+/// Overrides are looked up in either order. Merged bodies get new names and fall back to the default.
+#[derive(Clone, Debug, Default)]
+pub struct CollisionPolicy {
+    pub default: Response,
+    pub pairs: Vec<(String, String, Response)>,
+}
+
+impl CollisionPolicy {
+    pub fn new(default: Response) -> Self {
+        CollisionPolicy { default, pairs: Vec::new() }
+    }
+
+    pub fn set(&mut self, a: &str, b: &str, response: Response) {
+        self.pairs.retain(|(x, y, _)| !((x == a && y == b) || (x == b && y == a)));
+        self.pairs.push((a.to_string(), b.to_string(), response));
+    }
+
+    pub fn response(&self, a: &CelestialObject, b: &CelestialObject) -> Response {
+        if a.is_black_hole() || b.is_black_hole() || a.is_test_particle() || b.is_test_particle() {
+            return Response::Absorb;
+        }
+        self.pairs.iter()
+            .find(|(x, y, _)| (*x == a.name && *y == b.name) || (*x == b.name && *y == a.name))
+            .map(|(_, _, response)| *response)
+            .unwrap_or(self.default)
+    }
+}
+
+/// Record of two bodies touching.
+#[derive(Clone, Debug)]
+pub struct Collision {
+    pub time: f64,
+    pub bodies: (String, String), // heavier body first
+    pub response: Response,
+    pub products: Vec<String>, // names of the bodies that came out of it
+    pub position: Position, // center of mass of the pair at contact
+    pub relative_speed: f64,
+}
+
+/// Broad phase: every pair `(i, j)`, `i < j`, whose spheres overlap.
+///
+/// `quad_tree` must have been built from `bodies` (e.g. with `QuadTree::from_bodies`),
+/// so that its indices refer to positions in `bodies`.
+pub fn detect(bodies: &[CelestialObject], quad_tree: &QuadTree) -> Vec<(usize, usize)> {
+    let max_radius = bodies.iter().map(|body| body.radius).fold(0.0, f64::max);
+    if max_radius == 0.0 {
+        return Vec::new();
+    }
+
+    let mut pairs = Vec::new();
+    let mut candidates = Vec::new();
+    for (i, body) in bodies.iter().enumerate() {
+        let reach = body.radius + max_radius;
+        let area = Rectangle::new(body.position.x - reach, body.position.y - reach, 2.0 * reach, 2.0 * reach);
+
+        candidates.clear();
+        quad_tree.query(&area, &mut candidates);
+        for &j in &candidates {
+            if j > i && body.overlaps(&bodies[j]) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Perfectly inelastic merge conserving mass, momentum and center of mass.
+/// The merged body keeps the volume of both, so its radius is (r1³ + r2³)^(1/3), and the kind,
+/// softening and colour of the heavier one.
+pub fn merge(a: &CelestialObject, b: &CelestialObject) -> CelestialObject {
+    let (heavy, light) = if a.mass >= b.mass { (a, b) } else { (b, a) };
+    let mass = heavy.mass + light.mass;
+    let position = if mass > 0.0 {
+        Point2::from((heavy.position.coords * heavy.mass + light.position.coords * light.mass) / mass)
+    } else {
+        heavy.position
+    };
+    let velocity = if mass > 0.0 { (heavy.momentum() + light.momentum()) / mass } else { heavy.velocity };
+    let acceleration = if mass > 0.0 {
+        (heavy.acceleration * heavy.mass + light.acceleration * light.mass) / mass
+    } else {
+        heavy.acceleration
+    };
+
+    let mut merged = CelestialObject::new(
+        format!("{}+{}", heavy.name, light.name),
+        mass,
+        position,
+        velocity,
+        acceleration,
+        position,
+    );
+    merged.radius = (heavy.radius.powi(3) + light.radius.powi(3)).cbrt();
+    merged.kind = heavy.kind;
+    merged.softening = heavy.softening;
+    merged.colour = heavy.colour;
+    merged
+}
+
+/// Adds the mass and momentum of `prey` to `predator`, which moves to the common center of mass.
+/// A black hole's horizon grows in proportion to its mass, whatever units the bodies are in.
+pub fn absorb(predator: &CelestialObject, prey: &CelestialObject) -> CelestialObject {
+    let mut merged = merge(predator, prey);
+    merged.name = predator.name.clone();
+    merged.kind = predator.kind;
+    if predator.is_black_hole() && predator.mass > 0.0 {
+        merged.radius = predator.radius * merged.mass / predator.mass;
+    } else {
+        merged.radius = predator.radius.max(merged.radius);
+    }
+    merged
+}
+
+/// Whether `other` has fallen into `hole`, i.e. its center lies inside the event horizon,
+/// which is the hole's `radius`.
+pub fn crossed_horizon(hole: &CelestialObject, other: &CelestialObject) -> bool {
+    hole.is_black_hole() && hole.get_distance(&other.position).norm() < hole.radius
+}
+
+/// Changes the velocities of two touching bodies by an impulse along the line between their centers,
+/// then pushes them apart until they just touch. Momentum and center of mass are conserved.
+pub fn bounce(a: &mut CelestialObject, b: &mut CelestialObject, restitution: f64) {
+    let offset = b.position - a.position;
+    let distance = offset.norm();
+    if distance == 0.0 || a.mass <= 0.0 || b.mass <= 0.0 {
+        return;
+    }
+    let normal = offset / distance;
+    let reduced = 1.0 / (1.0 / a.mass + 1.0 / b.mass);
+
+    let approach = (a.velocity - b.velocity).dot(&normal);
+    if approach > 0.0 {
+        let impulse = (1.0 + restitution) * approach * reduced;
+        a.velocity -= normal * impulse / a.mass;
+        b.velocity += normal * impulse / b.mass;
+    }
+
+    let overlap = a.radius + b.radius - distance;
+    if overlap > 0.0 {
+        a.position -= normal * overlap * reduced / a.mass;
+        b.position += normal * overlap * reduced / b.mass;
+    }
+}
+
+/// Shatters `light` against `heavy` into `pieces` equal debris.
+///
+/// The pair first collides perfectly inelastically along the contact normal. Half of the kinetic
+/// energy lost in that is handed to the debris as a symmetric radial spread, so the debris carry
+/// exactly the momentum of the light body. The debris ring is placed clear of `heavy`, which is
+/// moved back to keep the center of mass where it was.
+///
+/// Returns no debris, and leaves `heavy` untouched, for fewer than two pieces or a pair that cannot
+/// be separated (coincident centers or a massless body).
+pub fn fragment(heavy: &mut CelestialObject, light: &CelestialObject, pieces: usize) -> Vec<CelestialObject> {
+    let offset = light.position - heavy.position;
+    let distance = offset.norm();
+    if pieces < 2 || distance == 0.0 || heavy.mass <= 0.0 || light.mass <= 0.0 {
+        return Vec::new();
+    }
+    let mut light = light.clone();
+    let normal = offset / distance;
+    let reduced = 1.0 / (1.0 / heavy.mass + 1.0 / light.mass);
+    let approach = (heavy.velocity - light.velocity).dot(&normal).max(0.0);
+    let dissipated = 0.5 * reduced * approach * approach;
+    bounce(heavy, &mut light, 0.0);
+
+    let n = pieces as f64;
+    let piece_radius = light.radius / n.cbrt();
+    let ring = light.radius.max(CLEARANCE * piece_radius / (std::f64::consts::PI / n).sin());
+    let spread = (dissipated / light.mass).sqrt();
+
+    // separate the ring from the survivor, mass weighted so the center of mass stays put
+    let separation = CLEARANCE * (heavy.radius + ring + piece_radius) - (light.position - heavy.position).norm();
+    if separation > 0.0 {
+        let total = heavy.mass + light.mass;
+        heavy.position -= normal * separation * light.mass / total;
+        light.position += normal * separation * heavy.mass / total;
+    }
+
+    (0..pieces)
+        .map(|k| {
+            let angle = 2.0 * std::f64::consts::PI * k as f64 / n;
+            let direction = Vector2::new(angle.cos(), angle.sin());
+            CelestialObject::new(
+                format!("{}#{}", light.name, k + 1),
+                light.mass / n,
+                light.position + direction * ring,
+                light.velocity + direction * spread,
+                light.acceleration,
+                light.position + direction * ring,
+            )
+            .with_radius(piece_radius)
+        })
+        .collect()
+}
+
+/// Applies the policy's response to every overlapping pair in place and returns what happened.
+///
+/// Merge chains (A touches B, B touches C) collapse into a single body. Bodies that only start
+/// overlapping because of an earlier response are picked up on the next call.
+pub fn resolve(bodies: &mut Vec<CelestialObject>, quad_tree: &QuadTree, time: f64, policy: &CollisionPolicy) -> Vec<Collision> {
+    let pairs = detect(bodies, quad_tree);
+    if pairs.is_empty() {
+        return Vec::new();
+    }
+
+    // union-find over body indices, each root holding the merged body
+    let mut parent: Vec<usize> = (0..bodies.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    let mut shattered = vec![false; bodies.len()];
+    let mut debris = Vec::new();
+
+    let mut collisions = Vec::new();
+    for (i, j) in pairs {
+        let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+        if ri == rj || shattered[ri] || shattered[rj] || !bodies[ri].overlaps(&bodies[rj]) {
+            continue;
+        }
+        if bodies[ri].is_test_particle() && bodies[rj].is_test_particle() {
+            continue; // they pass through each other
+        }
+        let (keep, other) = (ri.min(rj), ri.max(rj));
+        let (heavy, light) = if bodies[keep].mass >= bodies[other].mass { (keep, other) } else { (other, keep) };
+        let response = policy.response(&bodies[heavy], &bodies[light]);
+        if response == Response::Absorb && (bodies[heavy].is_black_hole() || bodies[light].is_black_hole())
+            && !crossed_horizon(&bodies[heavy], &bodies[light]) && !crossed_horizon(&bodies[light], &bodies[heavy]) {
+            // a star wider than the horizon touches it long before it falls in
+            continue;
+        }
+
+        let names = (bodies[heavy].name.clone(), bodies[light].name.clone());
+        let position = merge(&bodies[heavy], &bodies[light]).position;
+        let relative_speed = (bodies[heavy].velocity - bodies[light].velocity).norm();
+
+        let products = match response {
+            Response::Merge => {
+                bodies[keep] = merge(&bodies[keep], &bodies[other]);
+                parent[other] = keep;
+                vec![bodies[keep].name.clone()]
+            }
+            Response::Absorb => {
+                let (predator, prey) = if bodies[light].is_black_hole() && !bodies[heavy].is_black_hole() {
+                    (light, heavy)
+                } else {
+                    (heavy, light)
+                };
+                bodies[keep] = absorb(&bodies[predator], &bodies[prey]);
+                parent[other] = keep;
+                vec![bodies[keep].name.clone()]
+            }
+            Response::Bounce { restitution } => {
+                let (left, right) = bodies.split_at_mut(other);
+                bounce(&mut left[keep], &mut right[0], restitution);
+                vec![names.0.clone(), names.1.clone()]
+            }
+            Response::Fragment { pieces } => {
+                let light_body = bodies[light].clone();
+                let pieces = fragment(&mut bodies[heavy], &light_body, pieces);
+                if pieces.is_empty() {
+                    vec![names.0.clone(), names.1.clone()]
+                } else {
+                    shattered[light] = true;
+                    let mut products = vec![names.0.clone()];
+                    products.extend(pieces.iter().map(|piece| piece.name.clone()));
+                    debris.extend(pieces);
+                    products
+                }
+            }
+        };
+
+        collisions.push(Collision { time, bodies: names, response, products, position, relative_speed });
+    }
+
+    let mut index = 0;
+    bodies.retain(|_| {
+        let alive = parent[index] == index && !shattered[index];
+        index += 1;
+        alive
+    });
+    bodies.extend(debris);
+    collisions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::objects::{BodyKind, Gravity};
+
+    fn body(name: &str, mass: f64, position: [f64; 2], velocity: [f64; 2], radius: f64) -> CelestialObject {
+        let position = Point2::new(position[0], position[1]);
+        CelestialObject::new(name.into(), mass, position, Vector2::new(velocity[0], velocity[1]), Vector2::zeros(), position).with_radius(radius)
+    }
+
+    fn momentum(bodies: &[CelestialObject]) -> Vector2<f64> {
+        bodies.iter().map(CelestialObject::momentum).sum()
+    }
+
+    #[test]
+    fn merge_conserves_mass_momentum_and_centre_of_mass() {
+        let a = body("a", 3.0, [0.0, 0.0], [1.0, 2.0], 1.0);
+        let b = body("b", 1.0, [1.5, 0.5], [-2.0, 0.5], 1.0);
+        let merged = merge(&a, &b);
+        assert_eq!(merged.name, "a+b");
+        assert_eq!(merged.mass, 4.0);
+        assert!((merged.momentum() - momentum(&[a, b])).norm() < 1e-12);
+        assert!((merged.position - Point2::new(0.375, 0.125)).norm() < 1e-12);
+        assert!((merged.radius - 2f64.cbrt()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn merge_chains_collapse_into_one_body() {
+        // a touches b and b touches c, but a and c are apart
+        let mut bodies = vec![
+            body("a", 1.0, [0.0, 0.0], [0.0, 1.0], 1.0),
+            body("b", 2.0, [1.5, 0.0], [1.0, 0.0], 1.0),
+            body("c", 1.0, [3.0, 0.0], [0.0, -1.0], 1.0),
+            body("far", 1.0, [100.0, 0.0], [0.0, 0.0], 1.0),
+        ];
+        let before = (momentum(&bodies), bodies.iter().map(|body| body.mass).sum::<f64>());
+        let quad_tree = QuadTree::from_bodies(&bodies, 4);
+        let collisions = resolve(&mut bodies, &quad_tree, 0.0, &CollisionPolicy::default());
+
+        assert_eq!(collisions.len(), 2);
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[1].name, "far");
+        assert!((momentum(&bodies) - before.0).norm() < 1e-12);
+        assert_eq!(bodies.iter().map(|body| body.mass).sum::<f64>(), before.1);
+        assert!((bodies[0].position - Point2::new(1.5, 0.0)).norm() < 1e-12);
+    }
+
+    #[test]
+    fn elastic_bounce_conserves_energy_and_momentum() {
+        let mut a = body("a", 2.0, [0.0, 0.0], [1.0, 0.3], 1.0);
+        let mut b = body("b", 1.0, [1.8, 0.6], [-1.5, 0.0], 1.0);
+        let energy = a.kinetic_energy() + b.kinetic_energy();
+        let before = momentum(&[a.clone(), b.clone()]);
+        bounce(&mut a, &mut b, 1.0);
+
+        assert!((a.kinetic_energy() + b.kinetic_energy() - energy).abs() < 1e-12);
+        assert!((momentum(&[a.clone(), b.clone()]) - before).norm() < 1e-12);
+        assert!((a.velocity - b.velocity).dot(&(b.position - a.position)) < 0.0, "still approaching");
+        assert!(((b.position - a.position).norm() - 2.0).abs() < 1e-12, "pushed apart until they just touch");
+    }
+
+    #[test]
+    fn fragmentation_conserves_mass_and_momentum() {
+        let heavy = body("planet", 10.0, [0.0, 0.0], [0.0, 0.0], 1.0);
+        let light = body("moon", 1.0, [1.2, 0.0], [-3.0, 0.5], 0.5);
+        let before = (momentum(&[heavy.clone(), light.clone()]), merge(&heavy, &light).position);
+        let mut survivor = heavy.clone();
+        let debris = fragment(&mut survivor, &light, 6);
+
+        assert_eq!(debris.len(), 6);
+        let mut after = debris.clone();
+        after.push(survivor.clone());
+        assert!((after.iter().map(|body| body.mass).sum::<f64>() - 11.0).abs() < 1e-12);
+        assert!((momentum(&after) - before.0).norm() < 1e-12);
+        let centre = after.iter().map(|body| body.position.coords * body.mass).sum::<Vector2<f64>>() / 11.0;
+        assert!((centre - before.1.coords).norm() < 1e-12);
+        for (k, piece) in debris.iter().enumerate() {
+            assert!(!piece.overlaps(&survivor), "{} touches the survivor", piece.name);
+            assert!(debris[k + 1..].iter().all(|other| !piece.overlaps(other)), "{} touches other debris", piece.name);
+        }
+    }
+
+    #[test]
+    fn black_hole_absorbs_bodies_inside_its_horizon() {
+        let gravity = Gravity { g: 1.0, c: 1.0, softening: 0.0 };
+        let hole = CelestialObject::black_hole("hole".into(), 1.0, Point2::origin(), Vector2::zeros(), gravity);
+        assert_eq!(hole.radius, hole.horizon_radius(gravity));
+
+        // touching the horizon is not enough, the star's centre has to cross it
+        let star = body("star", 2.0, [2.5, 0.0], [0.0, 1.0], 1.0);
+        let mut bodies = vec![hole.clone(), star.clone()];
+        let quad_tree = QuadTree::from_bodies(&bodies, 4);
+        assert!(resolve(&mut bodies, &quad_tree, 0.0, &CollisionPolicy::default()).is_empty());
+        assert_eq!(bodies.len(), 2);
+
+        let star = body("star", 2.0, [1.5, 0.0], [0.0, 1.0], 1.0);
+        let mut bodies = vec![hole.clone(), star.clone()];
+        let quad_tree = QuadTree::from_bodies(&bodies, 4);
+        let collisions = resolve(&mut bodies, &quad_tree, 0.0, &CollisionPolicy::new(Response::Bounce { restitution: 1.0 }));
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].response, Response::Absorb);
+        assert_eq!(bodies.len(), 1);
+        let grown = &bodies[0];
+        assert_eq!((grown.name.as_str(), grown.kind, grown.mass), ("hole", BodyKind::BlackHole, 3.0));
+        assert!((grown.momentum() - star.momentum()).norm() < 1e-12);
+        assert!((grown.radius - grown.horizon_radius(gravity)).abs() < 1e-12);
+    }
+}
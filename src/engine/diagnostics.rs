@@ -0,0 +1,145 @@
+use crate::engine::physics::QuadTree;
+use crate::space::objects::{CelestialObject, Gravity, Position, Velocity};
+
+use nalgebra::{Point2, Vector2};
+
+/// Conserved quantities of a set of bodies at one instant.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Diagnostics {
+    pub time: f64,
+    pub kinetic: f64,
+    pub potential: f64,
+    pub momentum: Vector2<f64>,
+    pub angular_momentum: f64, // about the barycentre, z component
+    pub virial_ratio: f64, // 2K / |U|, 1 for a relaxed bound system
+    pub momentum_scale: f64, // Σ m|v|, what momentum errors are measured against
+    pub angular_momentum_scale: f64, // Σ m|r × v| about the barycentre
+}
+
+/// Relative change of the conserved quantities since a reference `Diagnostics`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Drift {
+    pub energy: f64,
+    pub momentum: f64,
+    pub angular_momentum: f64,
+}
+
+impl Diagnostics {
+    /// Measures with the exact O(n²) potential energy.
+    pub fn measure(bodies: &[CelestialObject], time: f64, gravity: Gravity) -> Self {
+        let mut potential = 0.0;
+        for i in 0..bodies.len() {
+            for j in (i + 1)..bodies.len() {
+                potential += bodies[i].get_softened_potential(&bodies[j], gravity);
+            }
+        }
+        Self::with_potential(bodies, time, potential)
+    }
+
+    /// Measures with the potential energy approximated by a Barnes-Hut tree, O(n log n).
+    pub fn measure_tree(bodies: &[CelestialObject], time: f64, gravity: Gravity, theta: f64) -> Self {
+        let quad_tree = QuadTree::from_bodies(bodies, 4);
+        let potential = bodies.iter()
+            .map(|body| quad_tree.potential(body, theta, gravity))
+            .sum::<f64>() / 2.0; // every pair was counted from both ends
+        Self::with_potential(bodies, time, potential)
+    }
+
+    fn with_potential(bodies: &[CelestialObject], time: f64, potential: f64) -> Self {
+        let kinetic = bodies.iter().map(CelestialObject::kinetic_energy).sum::<f64>();
+        let momentum = bodies.iter().map(CelestialObject::momentum).sum::<Vector2<f64>>();
+        let (center, drift) = barycentre(bodies);
+
+        let (mut angular_momentum, mut angular_momentum_scale) = (0.0, 0.0);
+        for body in bodies {
+            let r = body.position - center;
+            let v = body.velocity - drift;
+            let l = body.mass * r.perp(&v);
+            angular_momentum += l;
+            angular_momentum_scale += l.abs();
+        }
+
+        Diagnostics {
+            time,
+            kinetic,
+            potential,
+            momentum,
+            angular_momentum,
+            virial_ratio: if potential != 0.0 { 2.0 * kinetic / potential.abs() } else { 0.0 },
+            momentum_scale: bodies.iter().map(|body| body.mass * body.velocity.norm()).sum(),
+            angular_momentum_scale,
+        }
+    }
+
+    pub fn energy(&self) -> f64 {
+        self.kinetic + self.potential
+    }
+
+    /// Relative drift from `initial`, each quantity divided by its own scale at `initial`.
+    pub fn drift(&self, initial: &Diagnostics) -> Drift {
+        let relative = |change: f64, scale: f64| if scale != 0.0 { change / scale } else { change };
+        Drift {
+            energy: relative(self.energy() - initial.energy(), initial.energy().abs()),
+            momentum: relative((self.momentum - initial.momentum).norm(), initial.momentum_scale),
+            angular_momentum: relative(self.angular_momentum - initial.angular_momentum, initial.angular_momentum_scale),
+        }
+    }
+}
+
+/// Center of mass and its velocity.
+pub fn barycentre(bodies: &[CelestialObject]) -> (Position, Velocity) {
+    let mass = bodies.iter().map(|body| body.mass).sum::<f64>();
+    if mass == 0.0 {
+        return (Point2::origin(), Vector2::new(0.0, 0.0));
+    }
+    let center = bodies.iter().map(|body| body.position.coords * body.mass).sum::<Vector2<f64>>() / mass;
+    let velocity = bodies.iter().map(CelestialObject::momentum).sum::<Vector2<f64>>() / mass;
+    (Point2::from(center), velocity)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::integrator::VelocityVerlet;
+    use crate::engine::physics;
+    use crate::engine::solver::DirectSum;
+
+    /// Two unit masses a unit apart on a circular orbit around their barycentre at rest, G = 1.
+    fn pair() -> Vec<CelestialObject> {
+        let speed = 0.5f64.sqrt();
+        [(-0.5, -speed), (0.5, speed)]
+            .map(|(x, v)| {
+                let position = Point2::new(x, 0.0);
+                CelestialObject::new(format!("{x}"), 1.0, position, Vector2::new(0.0, v), Vector2::zeros(), position)
+            })
+            .to_vec()
+    }
+
+    #[test]
+    fn circular_orbit_is_virialised() {
+        let gravity = Gravity { g: 1.0, ..Gravity::default() };
+        let diagnostics = Diagnostics::measure(&pair(), 0.0, gravity);
+        assert!((diagnostics.virial_ratio - 1.0).abs() < 1e-12, "2K/|U| = {}", diagnostics.virial_ratio);
+        assert!((diagnostics.energy() + 0.5).abs() < 1e-12);
+        assert_eq!(diagnostics.momentum, Vector2::zeros());
+        assert!((diagnostics.angular_momentum - 0.5f64.sqrt()).abs() < 1e-12);
+
+        let tree = Diagnostics::measure_tree(&pair(), 0.0, gravity, 0.5);
+        assert!((tree.potential - diagnostics.potential).abs() < 1e-12);
+    }
+
+    #[test]
+    fn symmetric_orbit_barely_drifts() {
+        let gravity = Gravity { g: 1.0, ..Gravity::default() };
+        let mut bodies = pair();
+        let initial = Diagnostics::measure(&bodies, 0.0, gravity);
+        let period = std::f64::consts::PI * 2f64.sqrt(); // 2π √(a³ / G(m1 + m2))
+        let steps = 1000;
+        physics::simulate(&mut bodies, &DirectSum, &VelocityVerlet, gravity, period / steps as f64, 10 * steps);
+
+        let drift = Diagnostics::measure(&bodies, 10.0 * period, gravity).drift(&initial);
+        assert!(drift.energy.abs() < 1e-6, "energy drift {}", drift.energy);
+        assert!(drift.momentum < 1e-14, "momentum drift {}", drift.momentum);
+        assert!(drift.angular_momentum.abs() < 1e-12, "angular momentum drift {}", drift.angular_momentum);
+    }
+}
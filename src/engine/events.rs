@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::space::hierarchy::Hierarchy;
+use crate::space::objects::{CelestialObject, Distance, Position, Velocity};
+
+/// What an `EventDetector` looks for.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Watch {
+    /// Periapsis and apoapsis passages of every body around its parent in the hierarchy.
+    Apsides,
+    /// Closest approaches of any two bodies that come nearer than `threshold`.
+    CloseApproach { threshold: f64 },
+    /// Conjunctions and oppositions of any two bodies as seen from `observer`.
+    Alignments { observer: String },
+    /// Transits and eclipses seen from `observer`: a body passing in front of another.
+    Transits { observer: String },
+    /// Bodies becoming unbound from their parent in the hierarchy.
+    Escapes,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EventKind {
+    Periapsis { body: String, parent: String, distance: f64 },
+    Apoapsis { body: String, parent: String, distance: f64 },
+    CloseApproach { a: String, b: String, distance: f64 },
+    /// `a` and `b` in the same direction from `observer`.
+    Conjunction { observer: String, a: String, b: String },
+    /// `a` and `b` in opposite directions from `observer`.
+    Opposition { observer: String, a: String, b: String },
+    /// `front` crosses the line of sight from `observer` to `behind`. It is an eclipse when `front`
+    /// looks at least as large as `behind`, like the Moon in front of the Sun.
+    Transit { observer: String, front: String, behind: String, eclipse: bool },
+    /// `body` has reached escape speed from `parent`.
+    Escape { body: String, parent: String },
+}
+
+/// An event with the time it happened, found to a small fraction of the step it fell in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub time: f64,
+    pub kind: EventKind,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "t={:.6e} ", self.time)?;
+        match &self.kind {
+            EventKind::Periapsis { body, parent, distance } => write!(f, "{body} at periapsis around {parent}, r={distance:.4e}"),
+            EventKind::Apoapsis { body, parent, distance } => write!(f, "{body} at apoapsis around {parent}, r={distance:.4e}"),
+            EventKind::CloseApproach { a, b, distance } => write!(f, "{a} and {b} closest, d={distance:.4e}"),
+            EventKind::Conjunction { observer, a, b } => write!(f, "{a} in conjunction with {b} from {observer}"),
+            EventKind::Opposition { observer, a, b } => write!(f, "{a} in opposition to {b} from {observer}"),
+            EventKind::Transit { observer, front, behind, eclipse: true } => write!(f, "{front} eclipses {behind} from {observer}"),
+            EventKind::Transit { observer, front, behind, eclipse: false } => write!(f, "{front} transits {behind} from {observer}"),
+            EventKind::Escape { body, parent } => write!(f, "{body} escapes {parent}"),
+        }
+    }
+}
+
+/// Finds events between consecutive snapshots of the bodies.
+///
+/// Every watched quantity (radial velocity for apsides, energy for escapes, the angle between two
+/// lines of sight for alignments) is a function of the state, and an event is a sign change of it
+/// between two snapshots. Positions inside the step come from cubic Hermite interpolation of the
+/// positions and velocities at both ends, and the crossing is located on it by bisection, so
+/// events are timed far more finely than the step. Steps long enough for a quantity to change sign
+/// twice, e.g. a whole orbit, miss the pair of events. Test particles are ignored.
+#[derive(Clone, Debug, Default)]
+pub struct EventDetector {
+    pub watches: Vec<Watch>,
+    previous: Vec<CelestialObject>,
+    previous_time: f64,
+    parents: Vec<(String, String)>, // (body, parent) at the previous snapshot
+}
+
+/// The bodies at both ends of a step, by name.
+struct Interval<'a> {
+    before: HashMap<&'a str, &'a CelestialObject>,
+    after: HashMap<&'a str, &'a CelestialObject>,
+    start: f64,
+    dt: f64,
+}
+
+impl<'a> Interval<'a> {
+    /// Hermite-interpolated position and velocity of the named body at fraction `s` of the step.
+    fn state(&self, name: &str, s: f64) -> Option<(Position, Velocity)> {
+        let (a, b) = (self.before.get(name)?, self.after.get(name)?);
+        let dt = self.dt;
+        let (s2, s3) = (s * s, s * s * s);
+        let position = a.position.coords * (2.0 * s3 - 3.0 * s2 + 1.0)
+            + a.velocity * (dt * (s3 - 2.0 * s2 + s))
+            + b.position.coords * (-2.0 * s3 + 3.0 * s2)
+            + b.velocity * (dt * (s3 - s2));
+        let velocity = (a.position.coords * (6.0 * s2 - 6.0 * s) + b.position.coords * (-6.0 * s2 + 6.0 * s)) / dt
+            + a.velocity * (3.0 * s2 - 4.0 * s + 1.0)
+            + b.velocity * (3.0 * s2 - 2.0 * s);
+        Some((Position::from(position), velocity))
+    }
+
+    fn mass(&self, name: &str) -> f64 {
+        self.after.get(name).map_or(0.0, |body| body.mass)
+    }
+
+    fn radius(&self, name: &str) -> f64 {
+        self.after.get(name).map_or(0.0, |body| body.radius)
+    }
+
+    /// Position and velocity of `body` relative to `origin` at fraction `s` of the step.
+    fn relative(&self, body: &str, origin: &str, s: f64) -> Option<(Distance, Velocity)> {
+        let (p, v) = self.state(body, s)?;
+        let (q, w) = self.state(origin, s)?;
+        Some((p - q, v - w))
+    }
+
+    /// Fraction of the step at which `f` changes sign, given it does between 0 and 1.
+    fn crossing(f: impl Fn(f64) -> f64) -> f64 {
+        let (mut low, mut high) = (0.0, 1.0);
+        let negative = f(0.0) < 0.0;
+        for _ in 0..60 {
+            let middle = 0.5 * (low + high);
+            if (f(middle) < 0.0) == negative {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        0.5 * (low + high)
+    }
+
+    fn time(&self, s: f64) -> f64 {
+        self.start + s * self.dt
+    }
+}
+
+/// +1 for a rise through zero, -1 for a fall, 0 if the sign stays.
+fn sign_change(before: f64, after: f64) -> i32 {
+    if before < 0.0 && after >= 0.0 {
+        1
+    } else if before > 0.0 && after <= 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+impl EventDetector {
+    pub fn new(watches: Vec<Watch>) -> Self {
+        EventDetector { watches, ..Default::default() }
+    }
+
+    /// Events between the previous call and this state of `bodies` at `time`, in time order.
+    /// The first call only records the state. `g` is the gravitational constant in the bodies' units.
+    pub fn observe(&mut self, bodies: &[CelestialObject], time: f64, g: f64) -> Vec<Event> {
+        let current: Vec<CelestialObject> = bodies.iter()
+            .filter(|body| !body.is_test_particle())
+            .cloned()
+            .collect();
+
+        let mut events = Vec::new();
+        if !self.previous.is_empty() && time > self.previous_time {
+            let interval = Interval {
+                before: self.previous.iter().map(|body| (body.name.as_str(), body)).collect(),
+                after: current.iter().map(|body| (body.name.as_str(), body)).collect(),
+                start: self.previous_time,
+                dt: time - self.previous_time,
+            };
+            let names: Vec<&str> = current.iter()
+                .map(|body| body.name.as_str())
+                .filter(|name| interval.before.contains_key(name))
+                .collect();
+            for watch in &self.watches {
+                self.detect(watch, &interval, &names, g, &mut events);
+            }
+        }
+        events.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        if self.watches.iter().any(|watch| matches!(watch, Watch::Apsides | Watch::Escapes)) {
+            let hierarchy = Hierarchy::detect(&current, g);
+            self.parents = (0..current.len())
+                .filter_map(|i| Some((current[i].name.clone(), current[hierarchy.parent(i)?].name.clone())))
+                .collect();
+        }
+        self.previous = current;
+        self.previous_time = time;
+        events
+    }
+
+    fn detect(&self, watch: &Watch, interval: &Interval, names: &[&str], g: f64, events: &mut Vec<Event>) {
+        let at = |s: f64, kind: EventKind| Event { time: interval.time(s), kind };
+        match watch {
+            Watch::Apsides => {
+                for (body, parent) in &self.parents {
+                    let radial = |s: f64| interval.relative(body, parent, s).map_or(0.0, |(r, v)| r.dot(&v));
+                    let change = sign_change(radial(0.0), radial(1.0));
+                    if change == 0 {
+                        continue;
+                    }
+                    let s = Interval::crossing(radial);
+                    let distance = interval.relative(body, parent, s).map_or(0.0, |(r, _)| r.norm());
+                    let (body, parent) = (body.clone(), parent.clone());
+                    events.push(at(s, if change > 0 {
+                        EventKind::Periapsis { body, parent, distance }
+                    } else {
+                        EventKind::Apoapsis { body, parent, distance }
+                    }));
+                }
+            }
+            Watch::Escapes => {
+                for (body, parent) in &self.parents {
+                    let mu = g * (interval.mass(body) + interval.mass(parent));
+                    let energy = |s: f64| interval.relative(body, parent, s)
+                        .map_or(0.0, |(r, v)| v.norm_squared() / 2.0 - mu / r.norm());
+                    if sign_change(energy(0.0), energy(1.0)) > 0 {
+                        let s = Interval::crossing(energy);
+                        events.push(at(s, EventKind::Escape { body: body.clone(), parent: parent.clone() }));
+                    }
+                }
+            }
+            Watch::CloseApproach { threshold } => {
+                for (i, a) in names.iter().enumerate() {
+                    for b in &names[i + 1..] {
+                        let radial = |s: f64| interval.relative(b, a, s).map_or(0.0, |(r, v)| r.dot(&v));
+                        if sign_change(radial(0.0), radial(1.0)) <= 0 {
+                            continue;
+                        }
+                        let s = Interval::crossing(radial);
+                        let distance = interval.relative(b, a, s).map_or(f64::INFINITY, |(r, _)| r.norm());
+                        if distance < *threshold {
+                            events.push(at(s, EventKind::CloseApproach { a: a.to_string(), b: b.to_string(), distance }));
+                        }
+                    }
+                }
+            }
+            Watch::Alignments { observer } | Watch::Transits { observer } => {
+                let transits = matches!(watch, Watch::Transits { .. });
+                let others: Vec<&str> = names.iter().copied().filter(|name| name != observer).collect();
+                for (i, a) in others.iter().enumerate() {
+                    for b in &others[i + 1..] {
+                        let sight = |s: f64| -> Option<(Distance, Distance)> {
+                            Some((interval.relative(a, observer, s)?.0, interval.relative(b, observer, s)?.0))
+                        };
+                        let angle = |s: f64| sight(s).map_or(0.0, |(ra, rb)| ra.perp(&rb));
+                        if sign_change(angle(0.0), angle(1.0)) == 0 {
+                            continue;
+                        }
+                        let s = Interval::crossing(angle);
+                        let Some((ra, rb)) = sight(s) else { continue };
+                        let (observer, a, b) = (observer.clone(), a.to_string(), b.to_string());
+                        if !transits {
+                            events.push(at(s, if ra.dot(&rb) > 0.0 {
+                                EventKind::Conjunction { observer, a, b }
+                            } else {
+                                EventKind::Opposition { observer, a, b }
+                            }));
+                        } else if ra.dot(&rb) > 0.0 {
+                            // in a plane every inferior conjunction lines up the discs
+                            let (front, behind, near, far) = if ra.norm() < rb.norm() { (a, b, ra, rb) } else { (b, a, rb, ra) };
+                            let (front_radius, behind_radius) = (interval.radius(&front), interval.radius(&behind));
+                            let eclipse = front_radius > 0.0 && front_radius / near.norm() >= behind_radius / far.norm();
+                            events.push(at(s, EventKind::Transit { observer, front, behind, eclipse }));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::kepler;
+    use crate::space::orbits::OrbitalElements;
+
+    use nalgebra::{Point2, Vector2};
+
+    fn star() -> CelestialObject {
+        CelestialObject::new("Star".into(), 1.0, Point2::origin(), Vector2::zeros(), Vector2::zeros(), Point2::origin())
+    }
+
+    #[test]
+    fn apsides_of_a_kepler_orbit() {
+        // G = 1, eccentricity 0.5, starting a little past periapsis
+        let mean_anomaly = 0.3;
+        let elements = OrbitalElements::new(1.0, 0.5, 0.0, 0.0).at_mean_anomaly(mean_anomaly);
+        let mut star = star();
+        let mut planet = CelestialObject::from_elements("Planet".into(), 1e-6, &star, &elements, 1.0);
+        let period = elements.period(1.0 + 1e-6);
+
+        let mut detector = EventDetector::new(vec![Watch::Apsides]);
+        let steps = 200;
+        let dt = period / steps as f64;
+        let mut events = detector.observe(&[star.clone(), planet.clone()], 0.0, 1.0);
+        for step in 1..=3 * steps {
+            kepler::propagate_pair(&mut star, &mut planet, dt, 1.0);
+            events.extend(detector.observe(&[star.clone(), planet.clone()], step as f64 * dt, 1.0));
+        }
+
+        // apoapsis half an orbit after periapsis, which the planet passed at mean anomaly 0
+        let start = -mean_anomaly / (2.0 * std::f64::consts::PI) * period;
+        assert_eq!(events.len(), 6);
+        for (k, event) in events.iter().enumerate() {
+            let expected = start + (k + 1) as f64 * period / 2.0;
+            assert!((event.time - expected).abs() < 1e-6 * period, "event {k} at {}, expected {expected}", event.time);
+            match &event.kind {
+                EventKind::Apoapsis { body, parent, distance } if k % 2 == 0 => {
+                    assert_eq!((body.as_str(), parent.as_str()), ("Planet", "Star"));
+                    assert!((distance - elements.apoapsis()).abs() < 1e-6, "apoapsis at {distance}");
+                }
+                EventKind::Periapsis { distance, .. } if k % 2 == 1 => {
+                    assert!((distance - elements.periapsis()).abs() < 1e-6, "periapsis at {distance}");
+                }
+                kind => panic!("unexpected {kind:?} as event {k}"),
+            }
+        }
+    }
+
+    #[test]
+    fn escape_after_a_kick() {
+        let star = star();
+        let position = Point2::new(1.0, 0.0);
+        let bound = CelestialObject::new("Probe".into(), 1e-9, position, Vector2::new(0.0, 1.0), Vector2::zeros(), position);
+        let mut detector = EventDetector::new(vec![Watch::Escapes]);
+        assert!(detector.observe(&[star.clone(), bound.clone()], 0.0, 1.0).is_empty());
+
+        // moving on to 1.5 times the escape speed at the same place
+        let mut kicked = bound.clone();
+        kicked.velocity = Vector2::new(0.0, 1.5 * 2f64.sqrt());
+        let events = detector.observe(&[star.clone(), kicked], 0.01, 1.0);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Escape { body: "Probe".into(), parent: "Star".into() });
+        assert!(events[0].time > 0.0 && events[0].time < 0.01);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::space::objects::{Acceleration, CelestialObject, Position, Velocity};
+
+/// Accelerations of every body for a given configuration, in the same order as the bodies.
+pub type Field<'a> = dyn Fn(&[CelestialObject]) -> Vec<Acceleration> + 'a;
+
+/// A scheme advancing positions and velocities of all bodies by one time step.
+///
+/// Every implementation leaves the last evaluated acceleration in `CelestialObject::acceleration`
+/// and the position at the start of the step in `CelestialObject::prevposition`.
+pub trait Integrator {
+    fn name(&self) -> &'static str;
+    /// Order of the global error in `dt`.
+    fn order(&self) -> u32;
+    fn step(&self, bodies: &mut [CelestialObject], dt: f64, field: &Field);
+}
+
+/// Stores the acceleration of the current configuration on every body.
+pub fn refresh(bodies: &mut [CelestialObject], field: &Field) {
+    let accelerations = field(bodies);
+    for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+        body.acceleration = acceleration;
+    }
+}
+
+/// Semi-implicit (symplectic) Euler: kick with a(x), then drift with the new velocity.
+/// One force evaluation per step.
+pub struct SymplecticEuler;
+
+/// Velocity Verlet: drift with the stored acceleration, then kick with the average of old and new.
+/// Reuses the acceleration kept on the bodies, so only one force evaluation per step. That
+/// acceleration must be current: after bodies are added, removed, merged or moved between steps,
+/// `refresh` it first, as `StarSystem` and `Simulation` do whenever their generation changes.
+pub struct VelocityVerlet;
+
+/// Leapfrog kick-drift-kick. Same update as velocity Verlet, but a(x) is re-evaluated at the start
+/// of every step, so it stays correct when bodies are added, removed or edited between steps.
+pub struct LeapfrogKDK;
+
+/// Classic fourth-order Runge-Kutta. Not symplectic, four force evaluations per step.
+pub struct RungeKutta4;
+
+/// Yoshida's fourth-order symplectic integrator: three leapfrog substeps with weights w1, w0, w1.
+pub struct Yoshida4;
+
+impl Integrator for SymplecticEuler {
+    fn name(&self) -> &'static str {
+        "euler"
+    }
+
+    fn order(&self) -> u32 {
+        1
+    }
+
+    fn step(&self, bodies: &mut [CelestialObject], dt: f64, field: &Field) {
+        let accelerations = field(bodies);
+        for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+            body.prevposition = body.position;
+            body.acceleration = acceleration;
+            body.velocity += acceleration * dt;
+            body.position += body.velocity * dt;
+        }
+    }
+}
+
+impl Integrator for VelocityVerlet {
+    fn name(&self) -> &'static str {
+        "verlet"
+    }
+
+    fn order(&self) -> u32 {
+        2
+    }
+
+    fn step(&self, bodies: &mut [CelestialObject], dt: f64, field: &Field) {
+        // freshly created bodies carry no acceleration yet
+        if bodies.iter().all(|body| body.acceleration.norm_squared() == 0.0) {
+            refresh(bodies, field);
+        }
+
+        for body in bodies.iter_mut() {
+            body.prevposition = body.position;
+            body.position += body.velocity * dt + 0.5 * body.acceleration * dt * dt;
+        }
+        let accelerations = field(bodies);
+        for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+            body.velocity += 0.5 * (body.acceleration + acceleration) * dt;
+            body.acceleration = acceleration;
+        }
+    }
+}
+
+impl Integrator for LeapfrogKDK {
+    fn name(&self) -> &'static str {
+        "leapfrog"
+    }
+
+    fn order(&self) -> u32 {
+        2
+    }
+
+    fn step(&self, bodies: &mut [CelestialObject], dt: f64, field: &Field) {
+        let accelerations = field(bodies);
+        for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+            body.prevposition = body.position;
+            body.velocity += 0.5 * acceleration * dt;
+            body.position += body.velocity * dt;
+        }
+        let accelerations = field(bodies);
+        for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+            body.velocity += 0.5 * acceleration * dt;
+            body.acceleration = acceleration;
+        }
+    }
+}
+
+impl Integrator for RungeKutta4 {
+    fn name(&self) -> &'static str {
+        "rk4"
+    }
+
+    fn order(&self) -> u32 {
+        4
+    }
+
+    fn step(&self, bodies: &mut [CelestialObject], dt: f64, field: &Field) {
+        let x0: Vec<Position> = bodies.iter().map(|body| body.position).collect();
+        let v0: Vec<Velocity> = bodies.iter().map(|body| body.velocity).collect();
+        let mut stage = bodies.to_vec();
+
+        // k1
+        let a1 = field(&stage);
+        let v1 = v0.clone();
+
+        // k2, k3 at the midpoint, k4 at the end of the step
+        let mut derivatives = vec![(v1, a1)];
+        for weight in [0.5, 0.5, 1.0] {
+            let (v_prev, a_prev) = derivatives.last().unwrap();
+            let v_next: Vec<Velocity> = (0..bodies.len()).map(|i| v0[i] + a_prev[i] * weight * dt).collect();
+            for (i, body) in stage.iter_mut().enumerate() {
+                body.position = x0[i] + v_prev[i] * weight * dt;
+                body.velocity = v_next[i]; // for velocity dependent fields such as the post-Newtonian one
+            }
+            let a_next = field(&stage);
+            derivatives.push((v_next, a_next));
+        }
+
+        for (i, body) in bodies.iter_mut().enumerate() {
+            let [(v1, a1), (v2, a2), (v3, a3), (v4, a4)] = [0, 1, 2, 3].map(|k| (derivatives[k].0[i], derivatives[k].1[i]));
+            body.prevposition = body.position;
+            body.position = x0[i] + (v1 + 2.0 * v2 + 2.0 * v3 + v4) * dt / 6.0;
+            body.velocity = v0[i] + (a1 + 2.0 * a2 + 2.0 * a3 + a4) * dt / 6.0;
+            body.acceleration = a4;
+        }
+    }
+}
+
+impl Integrator for Yoshida4 {
+    fn name(&self) -> &'static str {
+        "yoshida4"
+    }
+
+    fn order(&self) -> u32 {
+        4
+    }
+
+    fn step(&self, bodies: &mut [CelestialObject], dt: f64, field: &Field) {
+        let cbrt2 = 2f64.cbrt();
+        let w1 = 1.0 / (2.0 - cbrt2);
+        let w0 = -cbrt2 / (2.0 - cbrt2);
+        let drifts = [w1 / 2.0, (w0 + w1) / 2.0, (w0 + w1) / 2.0, w1 / 2.0];
+        let kicks = [w1, w0, w1];
+
+        for body in bodies.iter_mut() {
+            body.prevposition = body.position;
+        }
+        for (i, &drift) in drifts.iter().enumerate() {
+            for body in bodies.iter_mut() {
+                body.position += body.velocity * drift * dt;
+            }
+            if let Some(&kick) = kicks.get(i) {
+                let accelerations = field(bodies);
+                for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+                    body.velocity += acceleration * kick * dt;
+                    body.acceleration = acceleration;
+                }
+            }
+        }
+    }
+}
+
+/// The integrator whose `name()` is `name`, e.g. from a scenario file.
+pub fn by_name(name: &str) -> Option<Box<dyn Integrator>> {
+    match name {
+        "euler" => Some(Box::new(SymplecticEuler)),
+        "verlet" => Some(Box::new(VelocityVerlet)),
+        "leapfrog" => Some(Box::new(LeapfrogKDK)),
+        "rk4" => Some(Box::new(RungeKutta4)),
+        "yoshida4" => Some(Box::new(Yoshida4)),
+        _ => None,
+    }
+}
@@ -1 +1,8 @@
-pub mod physics;
\ No newline at end of file
+pub mod physics;
+pub mod integrator;
+pub mod solver;
+pub mod collision;
+pub mod relativity;
+pub mod diagnostics;
+pub mod timestep;
+pub mod events;
\ No newline at end of file
@@ -1,4 +1,6 @@
-use crate::space::objects::{Acceleration, CelestialObject, Force, Velocity};
+use crate::engine::integrator::Integrator;
+use crate::engine::solver::ForceSolver;
+use crate::space::objects::{CelestialObject, Force, Gravity, Mass, Position};
 
 use nalgebra::{Point2, Vector2};
 
@@ -6,6 +8,10 @@ pub struct QuadTree {
     pub bounds: Rectangle,
     pub capacity: usize,
     pub bodies: Vec<CelestialObject>,
+    pub indices: Vec<usize>, // insertion order of each entry in `bodies`
+    pub len: usize, // number of bodies in this node and its children
+    pub mass: Mass, // total mass of every body in this node and its children
+    pub center_of_mass: Position, // mass-weighted center of the same bodies
     pub northwest: Option<Box<QuadTree>>,
     pub northeast: Option<Box<QuadTree>>,
     pub southwest: Option<Box<QuadTree>>,
@@ -18,6 +24,10 @@ impl QuadTree {
             bounds,
             capacity,
             bodies: Vec::new(),
+            indices: Vec::new(),
+            len: 0,
+            mass: 0.0,
+            center_of_mass: bounds.center(),
             northwest: None,
             northeast: None,
             southwest: None,
@@ -25,34 +35,86 @@ impl QuadTree {
         }
     }
 
+    /// Builds a tree whose root tightly covers `bodies`. Indices reported by `query` are positions in `bodies`.
+    pub fn from_bodies(bodies: &[CelestialObject], capacity: usize) -> Self {
+        let mut quad_tree = QuadTree::new(Rectangle::bounding(bodies, 0.1), capacity);
+        for body in bodies {
+            quad_tree.insert(body.clone());
+        }
+        quad_tree
+    }
+
+    /// Inserts `body`, doubling the root until it covers the body's position.
     pub fn insert(&mut self, body: CelestialObject) {
-        if self.bodies.len() < self.capacity {
+        while body.position.x.is_finite() && body.position.y.is_finite() && !self.bounds.contains(body.position) {
+            self.grow(body.position);
+        }
+        self.insert_contained(self.len, body);
+    }
+
+    fn insert_contained(&mut self, index: usize, body: CelestialObject) {
+        self.len += 1;
+        let mass = self.mass + body.mass;
+        if mass > 0.0 {
+            self.center_of_mass = Point2::from(
+                (self.center_of_mass.coords * self.mass + body.position.coords * body.mass) / mass,
+            );
+        }
+        self.mass = mass;
+
+        // coincident bodies would otherwise subdivide until the cell width underflows
+        let indivisible = self.bounds.w <= f64::EPSILON * (self.bounds.x.abs() + self.bounds.y.abs()).max(1.0);
+
+        if self.bodies.len() < self.capacity || indivisible {
             self.bodies.push(body);
+            self.indices.push(index);
         } else {
-            if self.northwest.is_none() {
-                self.subdivide();
-            }
-            let index = self.get_index(body.position);
-            match index {
-                0 => self.northwest.as_mut().unwrap().insert(body),
-                1 => self.northeast.as_mut().unwrap().insert(body),
-                2 => self.southwest.as_mut().unwrap().insert(body),
-                3 => self.southeast.as_mut().unwrap().insert(body),
+            self.subdivide();
+            match self.get_index(body.position) {
+                0 => self.northwest.as_mut().unwrap().insert_contained(index, body),
+                1 => self.northeast.as_mut().unwrap().insert_contained(index, body),
+                2 => self.southwest.as_mut().unwrap().insert_contained(index, body),
+                3 => self.southeast.as_mut().unwrap().insert_contained(index, body),
                 _ => unreachable!(),
             }
         }
     }
 
+    /// Replaces the root by one twice as wide, extended towards `point`, keeping the old root as a quadrant.
+    fn grow(&mut self, point: Point2<f64>) {
+        let Rectangle { x, y, w, h } = self.bounds;
+        let x = if point.x < x { x - w } else { x };
+        let y = if point.y < y { y - h } else { y };
+
+        let old = std::mem::replace(self, QuadTree::new(Rectangle::new(x, y, 2.0 * w, 2.0 * h), self.capacity));
+        self.len = old.len;
+        self.mass = old.mass;
+        self.center_of_mass = old.center_of_mass;
+
+        let index = self.get_index(old.bounds.center());
+        let slot = match index {
+            0 => &mut self.northwest,
+            1 => &mut self.northeast,
+            2 => &mut self.southwest,
+            3 => &mut self.southeast,
+            _ => unreachable!(),
+        };
+        *slot = Some(Box::new(old));
+        self.subdivide();
+    }
+
+    /// Creates whichever of the four children are still missing.
     pub fn subdivide(&mut self) {
         let x = self.bounds.x;
         let y = self.bounds.y;
         let w = self.bounds.w / 2.0;
         let h = self.bounds.h / 2.0;
+        let capacity = self.capacity;
 
-        self.northwest = Some(Box::new(QuadTree::new(Rectangle::new(x, y, w, h), self.capacity)));
-        self.northeast = Some(Box::new(QuadTree::new(Rectangle::new(x + w, y, w, h), self.capacity)));
-        self.southwest = Some(Box::new(QuadTree::new(Rectangle::new(x, y + h, w, h), self.capacity)));
-        self.southeast = Some(Box::new(QuadTree::new(Rectangle::new(x + w, y + h, w, h), self.capacity)));
+        self.northwest.get_or_insert_with(|| Box::new(QuadTree::new(Rectangle::new(x, y, w, h), capacity)));
+        self.northeast.get_or_insert_with(|| Box::new(QuadTree::new(Rectangle::new(x + w, y, w, h), capacity)));
+        self.southwest.get_or_insert_with(|| Box::new(QuadTree::new(Rectangle::new(x, y + h, w, h), capacity)));
+        self.southeast.get_or_insert_with(|| Box::new(QuadTree::new(Rectangle::new(x + w, y + h, w, h), capacity)));
     }
 
     pub fn get_index(&self, point: Point2<f64>) -> usize {
@@ -72,47 +134,96 @@ impl QuadTree {
         }
     }
 
-    pub fn calculate_force(&self, body: &CelestialObject, theta: f64) -> Force {
-        let distance = body.position - self.bounds.center();
+    /// Collects the indices of all bodies positioned inside `area`.
+    pub fn query(&self, area: &Rectangle, found: &mut Vec<usize>) {
+        if self.len == 0 || !self.bounds.intersects(area) {
+            return;
+        }
+        for (body, &index) in self.bodies.iter().zip(&self.indices) {
+            if area.contains(body.position) {
+                found.push(index);
+            }
+        }
+        for child in [&self.northwest, &self.northeast, &self.southwest, &self.southeast].into_iter().flatten() {
+            child.query(area, found);
+        }
+    }
+
+    /// Force exerted on `body` by this node treated as a single point mass at its center of mass,
+    /// softened like a pair of `body` and a body with the system's softening.
+    pub fn calculate_force(&self, body: &CelestialObject, gravity: Gravity) -> Force {
+        let distance = self.center_of_mass - body.position;
         let distance_squared = distance.norm_squared();
 
         if distance_squared == 0.0 {
             return Vector2::new(0.0, 0.0);
         }
 
-        let mass = self.total_mass();
-        let force = (crate::space::objects::G * body.mass * mass) / distance_squared;
-        force * distance.normalize()
+        let (own, softening) = (body.softening.unwrap_or(gravity.softening), gravity.softening);
+        let softened = distance_squared + 0.5 * (own * own + softening * softening);
+        distance * (gravity.g * body.mass * self.mass) / (softened * softened.sqrt())
     }
 
     pub fn total_mass(&self) -> f64 {
-        self.bodies.iter().map(|body| body.mass).sum::<f64>()
+        self.mass
     }
 
-    pub fn traverse(&self, body: &CelestialObject, theta: f64) -> Force {
-        let distance = body.position - self.bounds.center();
-        let distance_squared = distance.norm_squared();
-    
-        if self.bodies.len() == 1 {
-            self.calculate_force(body, theta)
-        } else if distance_squared > (self.bounds.w * theta).powi(2) {
-            self.calculate_force(body, theta)
-        } else {
-            let mut force = Vector2::new(0.0, 0.0);
-            if let Some(northwest) = &self.northwest {
-                force += northwest.traverse(body, theta);
-            }
-            if let Some(northeast) = &self.northeast {
-                force += northeast.traverse(body, theta);
-            }
-            if let Some(southwest) = &self.southwest {
-                force += southwest.traverse(body, theta);
-            }
-            if let Some(southeast) = &self.southeast {
-                force += southeast.traverse(body, theta);
-            }
-            force
+    /// Barnes-Hut force on `body`.
+    ///
+    /// A node whose width `s` seen from distance `d` to its center of mass satisfies `s / d < theta`
+    /// is approximated by its aggregate mass, otherwise its own bodies are summed exactly and
+    /// the children are opened. Nodes containing `body` are always opened, so a body never
+    /// attracts itself through an aggregate.
+    pub fn traverse(&self, body: &CelestialObject, theta: f64, gravity: Gravity) -> Force {
+        let mut force = Vector2::new(0.0, 0.0);
+        if self.mass == 0.0 {
+            return force;
+        }
+
+        let distance = (self.center_of_mass - body.position).norm();
+        if !self.bounds.contains(body.position) && self.bounds.w < theta * distance {
+            return self.calculate_force(body, gravity);
         }
+
+        for other in &self.bodies {
+            force += body.get_softened_force(other, gravity); // zero for the body itself
+        }
+        if let Some(northwest) = &self.northwest {
+            force += northwest.traverse(body, theta, gravity);
+        }
+        if let Some(northeast) = &self.northeast {
+            force += northeast.traverse(body, theta, gravity);
+        }
+        if let Some(southwest) = &self.southwest {
+            force += southwest.traverse(body, theta, gravity);
+        }
+        if let Some(southeast) = &self.southeast {
+            force += southeast.traverse(body, theta, gravity);
+        }
+        force
+    }
+
+    /// Barnes-Hut gravitational potential energy of `body` in the field of every other body,
+    /// with the same opening criterion as `traverse`.
+    pub fn potential(&self, body: &CelestialObject, theta: f64, gravity: Gravity) -> f64 {
+        if self.mass == 0.0 {
+            return 0.0;
+        }
+
+        let distance = (self.center_of_mass - body.position).norm();
+        if !self.bounds.contains(body.position) && self.bounds.w < theta * distance {
+            let (own, softening) = (body.softening.unwrap_or(gravity.softening), gravity.softening);
+            let softened = distance * distance + 0.5 * (own * own + softening * softening);
+            return -gravity.g * body.mass * self.mass / softened.sqrt();
+        }
+
+        let mut potential = self.bodies.iter()
+            .map(|other| body.get_softened_potential(other, gravity))
+            .sum::<f64>();
+        for child in [&self.northwest, &self.northeast, &self.southwest, &self.southeast].into_iter().flatten() {
+            potential += child.potential(body, theta, gravity);
+        }
+        potential
     }
 }
 
@@ -132,31 +243,50 @@ impl Rectangle {
     pub fn center(&self) -> Point2<f64> {
         Point2::new(self.x + self.w / 2.0, self.y + self.h / 2.0)
     }
-}
 
-pub fn calculate_force(body: &CelestialObject, quad_tree: &QuadTree, theta: f64) -> Force {
-    quad_tree.traverse(body, theta)
-}
+    /// Smallest square covering every body, enlarged by `padding` (a fraction of its width) on each side.
+    pub fn bounding(bodies: &[CelestialObject], padding: f64) -> Self {
+        let mut min = Point2::new(f64::INFINITY, f64::INFINITY);
+        let mut max = Point2::new(f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for body in bodies.iter().filter(|body| body.position.x.is_finite() && body.position.y.is_finite()) {
+            min = min.inf(&body.position);
+            max = max.sup(&body.position);
+        }
+        if min.x > max.x {
+            return Rectangle::new(-1.0, -1.0, 2.0, 2.0);
+        }
+
+        let mut size = (max.x - min.x).max(max.y - min.y);
+        if size == 0.0 {
+            size = 1.0;
+        }
+        let size = size * (1.0 + 2.0 * padding);
+        let center = nalgebra::center(&min, &max);
+        Rectangle::new(center.x - size / 2.0, center.y - size / 2.0, size, size)
+    }
+
+    pub fn contains(&self, point: Point2<f64>) -> bool {
+        point.x >= self.x && point.x <= self.x + self.w && point.y >= self.y && point.y <= self.y + self.h
+    }
 
-pub fn update_body(body: &mut CelestialObject, force: Force, dt: f64) {
-    body.acceleration = force / body.mass;
-    body.velocity += 0.5 * body.acceleration * dt; // Verlet integration 
-    body.position += body.velocity * dt;
-    body.prevposition = body.position - body.velocity * dt;
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        self.x <= other.x + other.w && other.x <= self.x + self.w && self.y <= other.y + other.h && other.y <= self.y + self.h
+    }
 }
 
-pub fn simulate(bodies: &mut Vec<CelestialObject>, dt: f64, num_steps: usize, theta: f64) {
-    let mut quad_tree = QuadTree::new(Rectangle::new(-1.0, -1.0, 2.0, 2.0), 4);
+pub fn calculate_force(body: &CelestialObject, quad_tree: &QuadTree, theta: f64, gravity: Gravity) -> Force {
+    quad_tree.traverse(body, theta, gravity)
+}
 
+pub fn simulate(
+    bodies: &mut [CelestialObject],
+    solver: &dyn ForceSolver,
+    integrator: &dyn Integrator,
+    gravity: Gravity,
+    dt: f64,
+    num_steps: usize,
+) {
     for _ in 0..num_steps {
-        quad_tree = QuadTree::new(Rectangle::new(-1.0, -1.0, 2.0, 2.0), 4);
-        for body in &*bodies {
-            quad_tree.insert(body.clone());
-        }
-
-        for body in bodies.iter_mut() {
-            let force = calculate_force(body, &quad_tree, theta);
-            update_body(body, force, dt);
-        }
+        integrator.step(bodies, dt, &|bodies| solver.accelerations(bodies, gravity));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,164 @@
+use crate::engine::integrator::Integrator;
+use crate::engine::solver::{ForceSolver, SolverSpec};
+use crate::space::objects::{Acceleration, CelestialObject, Force, Gravity};
+
+use nalgebra::Vector2;
+
+/// First post-Newtonian gravity (Einstein-Infeld-Hoffmann equations).
+///
+/// The Newtonian part comes from `newtonian`, so it can be a tree or an exact solver. The 1/c²
+/// corrections depend on velocities and on the Newtonian accelerations, and are always summed
+/// exactly over all pairs, O(n²). They are ~GM/(rc²) of the Newtonian force, 1e-8 for Mercury.
+/// Softening only applies to the Newtonian part.
+pub struct PostNewtonian {
+    pub newtonian: Box<dyn ForceSolver>,
+}
+
+impl PostNewtonian {
+    pub fn new(newtonian: Box<dyn ForceSolver>) -> Self {
+        PostNewtonian { newtonian }
+    }
+}
+
+impl ForceSolver for PostNewtonian {
+    fn name(&self) -> &'static str {
+        "1pn"
+    }
+
+    fn spec(&self) -> SolverSpec {
+        SolverSpec::PostNewtonian(Box::new(self.newtonian.spec()))
+    }
+
+    fn forces(&self, bodies: &[CelestialObject], gravity: Gravity) -> Vec<Force> {
+        self.accelerations(bodies, gravity)
+            .into_iter()
+            .zip(bodies)
+            .map(|(acceleration, body)| acceleration * body.mass)
+            .collect()
+    }
+
+    fn accelerations(&self, bodies: &[CelestialObject], gravity: Gravity) -> Vec<Acceleration> {
+        let newtonian = self.newtonian.accelerations(bodies, gravity);
+        let corrections = eih_corrections(bodies, &newtonian, gravity);
+        newtonian.iter().zip(corrections).map(|(a, da)| a + da).collect()
+    }
+}
+
+/// 1/c² terms of the EIH acceleration of every body, given their Newtonian accelerations.
+///
+/// For body a, with n = x_b - x_a, r = |n| and φ_a = Σ_{c≠a} G m_c / r_ac:
+/// ```text
+/// Σ_b G m_b n / r³ [-4φ_a - φ_b + v_a² + 2v_b² - 4 v_a·v_b - 3/2 (n·v_b / r)² + 1/2 n·a_b]
+/// + Σ_b G m_b / r³ [-n·(4v_a - 3v_b)] (v_a - v_b)
+/// + 7/2 Σ_b G m_b a_b / r
+/// ```
+/// all divided by c², with G and c taken from `gravity`.
+pub fn eih_corrections(bodies: &[CelestialObject], newtonian: &[Acceleration], gravity: Gravity) -> Vec<Acceleration> {
+    let (g, c) = (gravity.g, gravity.c);
+    let potentials: Vec<f64> = bodies.iter()
+        .enumerate()
+        .map(|(a, body)| {
+            bodies.iter()
+                .enumerate()
+                .filter(|&(c, other)| c != a && other.position != body.position)
+                .map(|(_, other)| g * other.mass / body.get_distance(&other.position).norm())
+                .sum()
+        })
+        .collect();
+
+    let c2 = c * c;
+    bodies.iter()
+        .enumerate()
+        .map(|(a, body)| {
+            let mut correction = Vector2::new(0.0, 0.0);
+            for (b, other) in bodies.iter().enumerate() {
+                let n = body.get_distance(&other.position);
+                let r = n.norm();
+                if b == a || r == 0.0 {
+                    continue;
+                }
+                let (va, vb, ab) = (body.velocity, other.velocity, newtonian[b]);
+                let gm = g * other.mass;
+
+                let bracket = -4.0 * potentials[a] - potentials[b]
+                    + va.norm_squared() + 2.0 * vb.norm_squared() - 4.0 * va.dot(&vb)
+                    - 1.5 * (n.dot(&vb) / r).powi(2)
+                    + 0.5 * n.dot(&ab);
+                correction += n * gm / r.powi(3) * bracket;
+                correction += (va - vb) * gm / r.powi(3) * (-n).dot(&(4.0 * va - 3.0 * vb));
+                correction += ab * 3.5 * gm / r;
+            }
+            correction / c2
+        })
+        .collect()
+}
+
+/// Direction of periapsis of `body` around `primary`, from the Laplace-Runge-Lenz vector, in radians.
+/// `g` is the gravitational constant in the units of the bodies, as in `kepler`.
+pub fn periapsis_longitude(primary: &CelestialObject, body: &CelestialObject, g: f64) -> f64 {
+    let mu = g * (primary.mass + body.mass);
+    let r = body.position - primary.position;
+    let v = body.velocity - primary.velocity;
+    let eccentricity = ((v.norm_squared() - mu / r.norm()) * r - r.dot(&v) * v) / mu;
+    eccentricity.y.atan2(eccentricity.x)
+}
+
+/// Relativistic periapsis precession of the body named `name` around `bodies[0]`, in arcseconds per century.
+///
+/// Runs the bodies, given in SI, side by side for `years`, once with `newtonian` and once with `PostNewtonian` on top
+/// of it, so precession caused by the other bodies cancels out. The difference of periapsis directions
+/// is sampled every step and the rate is its least squares slope, which averages out the short period
+/// wobble of nearly circular orbits. On `StarSystem::solar()` this gives the classic ~43"/century for Mercury.
+pub fn relativistic_precession(
+    bodies: &[CelestialObject],
+    name: &str,
+    newtonian: Box<dyn ForceSolver>,
+    integrator: &dyn Integrator,
+    dt: f64,
+    years: f64,
+) -> Option<f64> {
+    let index = bodies.iter().position(|body| body.name == name)?;
+    let steps = (years * 365.25 * 86400.0 / dt).round() as usize;
+    let relativistic = PostNewtonian::new(newtonian);
+    let gravity = Gravity::default();
+    let mut classic = bodies.to_vec();
+    let mut perturbed = bodies.to_vec();
+
+    // running sums for the least squares fit of shift(t) = rate * t + offset
+    let (mut st, mut ss, mut stt, mut sts) = (0.0, 0.0, 0.0, 0.0);
+    let mut unwrapped = 0.0;
+    for step in 1..=steps {
+        integrator.step(&mut classic, dt, &|bodies| relativistic.newtonian.accelerations(bodies, gravity));
+        integrator.step(&mut perturbed, dt, &|bodies| relativistic.accelerations(bodies, gravity));
+
+        let shift = periapsis_longitude(&perturbed[0], &perturbed[index], gravity.g)
+            - periapsis_longitude(&classic[0], &classic[index], gravity.g);
+        let turns = ((unwrapped - shift) / (2.0 * std::f64::consts::PI)).round();
+        unwrapped = shift + turns * 2.0 * std::f64::consts::PI;
+
+        let t = step as f64 * dt;
+        st += t;
+        ss += unwrapped;
+        stt += t * t;
+        sts += t * unwrapped;
+    }
+    let n = steps as f64;
+    let rate = (n * sts - st * ss) / (n * stt - st * st); // radians per second
+    Some(rate.to_degrees() * 3600.0 * 100.0 * 365.25 * 86400.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::integrator::VelocityVerlet;
+    use crate::engine::solver::DirectSum;
+    use crate::space::system::StarSystem;
+
+    #[test]
+    fn mercury_precesses_43_arcseconds_per_century() {
+        // the Newtonian run alongside takes out the ~530"/century the other planets cause
+        let bodies = StarSystem::solar().bodies;
+        let rate = relativistic_precession(&bodies, "Mercury", Box::new(DirectSum), &VelocityVerlet, 7200.0, 10.0).unwrap();
+        assert!((rate - 43.0).abs() < 1.0, "{rate}\"/century");
+    }
+}
@@ -0,0 +1,242 @@
+use crate::engine::physics::QuadTree;
+use crate::engine::relativity::PostNewtonian;
+use crate::space::objects::{Acceleration, CelestialObject, Force, Gravity};
+
+use nalgebra::Vector2;
+
+/// A backend computing the gravitational force acting on every body.
+///
+/// `gravity` holds G in the system's units and the system wide Plummer length ε; every backend
+/// softens pairs the same way, through `CelestialObject::get_softened_force`, honouring per-body overrides.
+///
+/// Test particles have no mass, so they neither exert nor receive forces; `accelerations` leaves
+/// them out of the sources and gives them the acceleration of `field` instead.
+pub trait ForceSolver {
+    fn name(&self) -> &'static str;
+    /// Backend and parameters, enough to build an identical solver again.
+    fn spec(&self) -> SolverSpec;
+    /// Net force on every body, in the same order as `bodies`.
+    fn forces(&self, bodies: &[CelestialObject], gravity: Gravity) -> Vec<Force>;
+
+    /// Gravitational acceleration at every probe from the `sources` alone, whatever the probes' masses.
+    /// The default sums every source exactly, O(probes · sources).
+    fn field(&self, sources: &[CelestialObject], probes: &[CelestialObject], gravity: Gravity) -> Vec<Acceleration> {
+        probes.iter()
+            .map(|probe| {
+                let unit = CelestialObject { mass: 1.0, ..probe.clone() };
+                sources.iter().map(|source| unit.get_softened_force(source, gravity)).sum()
+            })
+            .collect()
+    }
+
+    fn accelerations(&self, bodies: &[CelestialObject], gravity: Gravity) -> Vec<Acceleration> {
+        if bodies.iter().any(CelestialObject::is_test_particle) {
+            let (sources, probes) = split_test_particles(bodies);
+            let massive = self.accelerations(&sources, gravity);
+            let test = self.field(&sources, &probes, gravity);
+            return merge_test_particles(bodies.iter(), massive, test);
+        }
+        self.forces(bodies, gravity)
+            .into_iter()
+            .zip(bodies)
+            .map(|(force, body)| force / body.mass)
+            .collect()
+    }
+
+    /// Net force on the bodies at the `active` indices only, in that order, from all bodies.
+    /// Backends that can skip the rest override this; the default computes every force.
+    fn forces_on(&self, bodies: &[CelestialObject], active: &[usize], gravity: Gravity) -> Vec<Force> {
+        let forces = self.forces(bodies, gravity);
+        active.iter().map(|&i| forces[i]).collect()
+    }
+
+    fn accelerations_on(&self, bodies: &[CelestialObject], active: &[usize], gravity: Gravity) -> Vec<Acceleration> {
+        if bodies.iter().any(CelestialObject::is_test_particle) {
+            let (sources, _) = split_test_particles(bodies);
+            // index of every massive body among the sources
+            let mut source_index = vec![0; bodies.len()];
+            let mut next = 0;
+            for (i, body) in bodies.iter().enumerate() {
+                if !body.is_test_particle() {
+                    source_index[i] = next;
+                    next += 1;
+                }
+            }
+            let massive_active: Vec<usize> = active.iter()
+                .filter(|&&i| !bodies[i].is_test_particle())
+                .map(|&i| source_index[i])
+                .collect();
+            let probes: Vec<CelestialObject> = active.iter()
+                .filter(|&&i| bodies[i].is_test_particle())
+                .map(|&i| bodies[i].clone())
+                .collect();
+            let massive = self.accelerations_on(&sources, &massive_active, gravity);
+            let test = self.field(&sources, &probes, gravity);
+            return merge_test_particles(active.iter().map(|&i| &bodies[i]), massive, test);
+        }
+        self.forces_on(bodies, active, gravity)
+            .into_iter()
+            .zip(active)
+            .map(|(force, &i)| force / bodies[i].mass)
+            .collect()
+    }
+}
+
+/// Description of a solver and its parameters, for scenario files and checkpoints.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SolverSpec {
+    Direct,
+    BarnesHut { theta: f64, capacity: usize },
+    PostNewtonian(Box<SolverSpec>),
+}
+
+impl SolverSpec {
+    pub fn build(&self) -> Box<dyn ForceSolver> {
+        match self {
+            SolverSpec::Direct => Box::new(DirectSum),
+            SolverSpec::BarnesHut { theta, capacity } => Box::new(BarnesHut { theta: *theta, capacity: *capacity }),
+            SolverSpec::PostNewtonian(newtonian) => Box::new(PostNewtonian::new(newtonian.build())),
+        }
+    }
+}
+
+/// Massive bodies and test particles, each in their original order.
+fn split_test_particles(bodies: &[CelestialObject]) -> (Vec<CelestialObject>, Vec<CelestialObject>) {
+    bodies.iter().cloned().partition(|body| !body.is_test_particle())
+}
+
+/// Interleaves the accelerations of the massive bodies and of the test particles back into the order of `order`.
+fn merge_test_particles<'a>(
+    order: impl Iterator<Item = &'a CelestialObject>,
+    massive: Vec<Acceleration>,
+    test: Vec<Acceleration>,
+) -> Vec<Acceleration> {
+    let (mut massive, mut test) = (massive.into_iter(), test.into_iter());
+    order
+        .map(|body| if body.is_test_particle() { test.next() } else { massive.next() }.unwrap())
+        .collect()
+}
+
+/// Exact pairwise summation, O(n²).
+/// Each pair is evaluated once and applied to both bodies with opposite signs (Newton's third law).
+pub struct DirectSum;
+
+/// Barnes-Hut approximation over a `QuadTree` rebuilt on every call, O(n log n).
+pub struct BarnesHut {
+    pub theta: f64, // opening angle, 0 degenerates to direct summation
+    pub capacity: usize, // bodies per node before it subdivides
+}
+
+impl BarnesHut {
+    pub fn new(theta: f64) -> Self {
+        BarnesHut { theta, capacity: 4 }
+    }
+}
+
+impl ForceSolver for DirectSum {
+    fn name(&self) -> &'static str {
+        "direct"
+    }
+
+    fn spec(&self) -> SolverSpec {
+        SolverSpec::Direct
+    }
+
+    fn forces(&self, bodies: &[CelestialObject], gravity: Gravity) -> Vec<Force> {
+        let mut forces = vec![Vector2::new(0.0, 0.0); bodies.len()];
+        for i in 0..bodies.len() {
+            for j in (i + 1)..bodies.len() {
+                let force = bodies[i].get_softened_force(&bodies[j], gravity);
+                forces[i] += force;
+                forces[j] -= force;
+            }
+        }
+        forces
+    }
+
+    /// O(n) per active body, so a substep with few active bodies costs far less than `forces`.
+    fn forces_on(&self, bodies: &[CelestialObject], active: &[usize], gravity: Gravity) -> Vec<Force> {
+        active.iter()
+            .map(|&i| bodies.iter().map(|other| bodies[i].get_softened_force(other, gravity)).sum())
+            .collect()
+    }
+}
+
+impl ForceSolver for BarnesHut {
+    fn name(&self) -> &'static str {
+        "barnes-hut"
+    }
+
+    fn spec(&self) -> SolverSpec {
+        SolverSpec::BarnesHut { theta: self.theta, capacity: self.capacity }
+    }
+
+    fn forces(&self, bodies: &[CelestialObject], gravity: Gravity) -> Vec<Force> {
+        let quad_tree = QuadTree::from_bodies(bodies, self.capacity);
+        bodies.iter()
+            .map(|body| quad_tree.traverse(body, self.theta, gravity))
+            .collect()
+    }
+
+    fn forces_on(&self, bodies: &[CelestialObject], active: &[usize], gravity: Gravity) -> Vec<Force> {
+        let quad_tree = QuadTree::from_bodies(bodies, self.capacity);
+        active.iter()
+            .map(|&i| quad_tree.traverse(&bodies[i], self.theta, gravity))
+            .collect()
+    }
+    /// One tree over the sources, then a walk per probe: O(probes · log sources).
+    fn field(&self, sources: &[CelestialObject], probes: &[CelestialObject], gravity: Gravity) -> Vec<Acceleration> {
+        let quad_tree = QuadTree::from_bodies(sources, self.capacity);
+        probes.iter()
+            .map(|probe| quad_tree.traverse(&CelestialObject { mass: 1.0, ..probe.clone() }, self.theta, gravity))
+            .collect()
+    }
+}
+
+/// Largest relative force error of `solver` against `reference` over all bodies.
+/// With `DirectSum` as the reference this measures the Barnes-Hut error for a given `theta`.
+pub fn max_relative_error(solver: &dyn ForceSolver, reference: &dyn ForceSolver, bodies: &[CelestialObject], gravity: Gravity) -> f64 {
+    solver.forces(bodies, gravity)
+        .iter()
+        .zip(reference.forces(bodies, gravity))
+        .filter(|(_, exact)| exact.norm() > 0.0)
+        .map(|(approx, exact)| (approx - exact).norm() / exact.norm())
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::objects::Position;
+
+    /// A heavy body in the middle of `count` light ones scattered over a unit square by a fixed
+    /// linear congruential sequence, so that no net force nearly cancels.
+    fn cluster(count: usize) -> Vec<CelestialObject> {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let centre = Position::new(0.5, 0.5);
+        let mut bodies = vec![CelestialObject::new("centre".into(), 1e3, centre, Vector2::zeros(), Vector2::zeros(), centre)];
+        bodies.extend((0..count).map(|i| {
+            let position = Position::new(next(), next());
+            CelestialObject::new(format!("{i}"), 1.0 + next(), position, Vector2::zeros(), Vector2::zeros(), position)
+        }));
+        bodies
+    }
+
+    fn error(theta: f64) -> f64 {
+        let gravity = Gravity { g: 1.0, c: 1.0, softening: 1e-3 };
+        max_relative_error(&BarnesHut::new(theta), &DirectSum, &cluster(400), gravity)
+    }
+
+    #[test]
+    fn barnes_hut_error_grows_with_theta() {
+        let (zero, half, one) = (error(0.0), error(0.5), error(1.0));
+        assert!(zero < 1e-12, "θ = 0 should match direct summation, error {zero}");
+        assert!(half < 0.02, "θ = 0.5 error {half}");
+        assert!(one < 0.15, "θ = 1 error {one}");
+        assert!(half < one);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::space::objects::{Acceleration, CelestialObject};
+
+use nalgebra::Vector2;
+
+/// Accelerations of the bodies at the `active` indices, in that order, for a given configuration.
+pub type ActiveField<'a> = dyn Fn(&[CelestialObject], &[usize]) -> Vec<Acceleration> + 'a;
+
+/// How the timestep a body wants is chosen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Criterion {
+    /// dt = eta * sqrt(length / |a|), with `length` typically the softening length or a small
+    /// fraction of the closest orbit.
+    Acceleration { eta: f64, length: f64 },
+    /// dt = eta * |a| / |da/dt| (Aarseth), with the jerk taken from the change of acceleration over
+    /// the body's last step. Scale free; eta ~ 0.002-0.01 suits the second order leapfrog used here.
+    Jerk { eta: f64 },
+}
+
+/// Individual per-body timesteps in power-of-two blocks (hierarchical block stepping).
+///
+/// A call to `step` advances all bodies by `dt`, the longest allowed step. Each body moves on its
+/// own step dt / 2^level, with `level` up to `max_level` chosen from `criterion`. All bodies drift
+/// together from one substep to the next, but forces are only recomputed for the bodies whose step
+/// ends on it, and each of them gets a kick-drift-kick leapfrog update over its own step. A body may
+/// refine its step at any time and coarsen it by one level where the coarser block begins, so
+/// every body is synchronized at the end of `step`.
+///
+/// The levels are kept between calls and the last accelerations are reused for the opening kick,
+/// like `VelocityVerlet`, so `reset` must be called whenever bodies are added, removed, merged or split.
+/// A change in the number of bodies is caught by `step`, which then starts afresh by itself.
+///
+/// With the jerk criterion a body that has just changed level keeps it for one more step, so that
+/// its jerk is measured again on the new level before it can move on; one noisy difference then
+/// cannot walk a body several levels in a row.
+#[derive(Clone, Debug)]
+pub struct BlockTimesteps {
+    pub criterion: Criterion,
+    pub max_level: u32, // the finest step is dt / 2^max_level
+    pub levels: Vec<u32>, // current level of every body, empty until the first step
+    pub moved: Vec<bool>, // whether each body changed level at its last evaluation, which voids its jerk
+    pub evaluations: usize, // forces computed on single bodies so far
+}
+
+impl BlockTimesteps {
+    pub fn new(criterion: Criterion, max_level: u32) -> Self {
+        BlockTimesteps { criterion, max_level, levels: Vec::new(), moved: Vec::new(), evaluations: 0 }
+    }
+
+    /// Forgets the levels and accelerations, so the next `step` evaluates every body afresh.
+    pub fn reset(&mut self) {
+        self.levels.clear();
+        self.moved.clear();
+    }
+
+    /// Timestep a body asks for; `jerk` is None when it is not known yet.
+    pub fn desired(&self, acceleration: Acceleration, jerk: Option<Vector2<f64>>) -> f64 {
+        match self.criterion {
+            Criterion::Acceleration { eta, length } => eta * (length / acceleration.norm()).sqrt(),
+            Criterion::Jerk { eta } => match jerk {
+                Some(jerk) => eta * acceleration.norm() / jerk.norm(),
+                None => 0.0, // start on the finest level until a jerk has been measured
+            },
+        }
+    }
+
+    /// The coarsest level whose step dt / 2^level does not exceed `desired`.
+    pub fn level(&self, dt: f64, desired: f64) -> u32 {
+        if desired.is_nan() || desired >= dt {
+            return 0;
+        }
+        let level = (dt / desired).log2().ceil();
+        if level >= self.max_level as f64 { self.max_level } else { level as u32 }
+    }
+
+    /// Step of the body at `index` for a block of length `dt`, None before the first step or for a
+    /// body that was not there at the last one.
+    pub fn timestep(&self, dt: f64, index: usize) -> Option<f64> {
+        self.levels.get(index).map(|&level| dt / (1u64 << level) as f64)
+    }
+
+    pub fn step(&mut self, bodies: &mut [CelestialObject], dt: f64, field: &ActiveField) {
+        if bodies.is_empty() {
+            return;
+        }
+        if self.levels.len() != bodies.len() {
+            let all: Vec<usize> = (0..bodies.len()).collect();
+            let accelerations = field(bodies, &all);
+            for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+                body.acceleration = acceleration;
+            }
+            self.evaluations += bodies.len();
+            self.levels = bodies.iter()
+                .map(|body| self.level(dt, self.desired(body.acceleration, None)))
+                .collect();
+            self.moved = vec![false; bodies.len()];
+        }
+
+        let max_level = self.max_level;
+        let ticks = 1usize << max_level;
+        let tick = dt / ticks as f64;
+        let stride = |level: u32| 1usize << (max_level - level); // ticks per step
+        let length = |level: u32| stride(level) as f64 * tick;
+
+        for (body, &level) in bodies.iter_mut().zip(&self.levels) {
+            body.prevposition = body.position;
+            body.velocity += body.acceleration * (0.5 * length(level));
+        }
+        let mut t = 0;
+        while t < ticks {
+            // jump straight to the next substep on which some body's step ends
+            let next = self.levels.iter()
+                .map(|&level| (t / stride(level) + 1) * stride(level))
+                .min()
+                .unwrap();
+            for body in bodies.iter_mut() {
+                body.position += body.velocity * ((next - t) as f64 * tick);
+            }
+            t = next;
+            let active: Vec<usize> = (0..bodies.len())
+                .filter(|&i| t % stride(self.levels[i]) == 0)
+                .collect();
+            let accelerations = field(bodies, &active);
+            self.evaluations += active.len();
+
+            for (&i, acceleration) in active.iter().zip(accelerations) {
+                let body = &mut bodies[i];
+                let current = self.levels[i];
+                body.velocity += acceleration * (0.5 * length(current));
+                let jerk = (acceleration - body.acceleration) / length(current);
+                body.acceleration = acceleration;
+
+                let mut level = match self.criterion {
+                    Criterion::Jerk { .. } if self.moved[i] => current,
+                    _ => self.level(dt, self.desired(acceleration, Some(jerk))),
+                };
+                if level < current {
+                    level = if t % stride(current - 1) == 0 { current - 1 } else { current };
+                }
+                self.moved[i] = level != current;
+                self.levels[i] = level;
+                if t < ticks {
+                    body.velocity += acceleration * (0.5 * length(level));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::kepler;
+    use crate::space::orbits::OrbitalElements;
+
+    use nalgebra::Point2;
+
+    /// Newtonian accelerations with G = 1, summed directly.
+    fn field(bodies: &[CelestialObject], active: &[usize]) -> Vec<Acceleration> {
+        active.iter()
+            .map(|&i| {
+                bodies.iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, other)| {
+                        let offset = other.position - bodies[i].position;
+                        offset * (other.mass / offset.norm().powi(3))
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// A star and a light planet starting at periapsis of an orbit with eccentricity 0.6, and its period.
+    fn pair() -> (Vec<CelestialObject>, f64) {
+        let star = CelestialObject::new("Star".into(), 1.0, Point2::origin(), Vector2::zeros(), Vector2::zeros(), Point2::origin());
+        let elements = OrbitalElements::new(1.0, 0.6, 0.0, 0.0);
+        let planet = CelestialObject::from_elements("Planet".into(), 1e-6, &star, &elements, 1.0);
+        let period = elements.period(1.0 + 1e-6);
+        (vec![star, planet], period)
+    }
+
+    #[test]
+    fn block_steps_follow_kepler() {
+        let (initial, period) = pair();
+        let mut bodies = initial.clone();
+        let mut timesteps = BlockTimesteps::new(Criterion::Jerk { eta: 0.003 }, 10);
+        let (blocks, orbits) = (50, 10);
+        let samples: Vec<Vec<CelestialObject>> = (0..orbits)
+            .map(|_| {
+                for _ in 0..blocks {
+                    timesteps.step(&mut bodies, period / blocks as f64, &field);
+                }
+                bodies.clone()
+            })
+            .collect();
+        let elapsed = (1..=orbits).map(|orbit| orbit as f64 * period);
+        let error = kepler::position_error(&initial, elapsed.zip(samples.iter().map(Vec::as_slice)), 1.0).unwrap();
+        assert!(error < 2e-3, "error {error} after {orbits} orbits");
+        // the block is far too long for the periapsis passage, so the planet must have been refined
+        assert!(timesteps.evaluations > 2 * blocks * orbits, "{} evaluations", timesteps.evaluations);
+    }
+
+    #[test]
+    fn levels_are_finer_at_periapsis() {
+        let (mut bodies, period) = pair();
+        let dt = period / 100.0;
+        let mut timesteps = BlockTimesteps::new(Criterion::Acceleration { eta: 0.02, length: 1.0 }, 8);
+        timesteps.step(&mut bodies, dt, &field);
+        let periapsis = timesteps.levels[1];
+        for _ in 1..50 {
+            timesteps.step(&mut bodies, dt, &field);
+        }
+        let apoapsis = timesteps.levels[1];
+        assert!(periapsis >= apoapsis + 2, "level {periapsis} at periapsis, {apoapsis} at apoapsis");
+        assert_eq!(timesteps.timestep(dt, 1), Some(dt / (1u64 << apoapsis) as f64));
+
+        // a body added without a reset starts the levels afresh instead of indexing past them
+        let far = Point2::new(100.0, 0.0);
+        bodies.push(CelestialObject::new("Far".into(), 1e-6, far, Vector2::zeros(), Vector2::zeros(), far));
+        timesteps.step(&mut bodies, dt, &field);
+        assert_eq!(timesteps.levels.len(), 3);
+    }
+}
@@ -1,15 +1,8 @@
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
 use ratatui_splash_screen::{SplashConfig, SplashScreen, SplashError};
 
-mod term;
-mod space;
-use crate::term::terminal::TerminalInterface;
-use crate::term::terminal::prelude::*;
-use crate::space::system::StarSystem;
+use gravity::engine::integrator::VelocityVerlet;
+use gravity::term::prelude::*;
+use gravity::space::system::StarSystem;
 
 static SPLASH: SplashConfig = SplashConfig {
     image_data: include_bytes!("../assets/splash.png"),
@@ -22,10 +15,9 @@ fn main() -> Result<(), std::io::Error> {
     let mut terminal_interface = TerminalInterface::new()?;
     let mut solar_system = StarSystem::solar();
 
-    terminal_interface.run(&solar_system)?;
+    let result = terminal_interface.run(&mut solar_system, &VelocityVerlet, 3600.0);
 
     // Clean up
-    disable_raw_mode()?;
-    execute!(terminal_interface.terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
-    Ok(())
-}
\ No newline at end of file
+    terminal_interface.restore()?;
+    result
+}
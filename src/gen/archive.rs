@@ -0,0 +1,850 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use nalgebra::{Point2, Vector2};
+
+use crate::space::objects::{BodyKind, CelestialObject};
+use crate::space::units::Units;
+
+/// First bytes of every archive.
+pub const MAGIC: &[u8; 8] = b"GRAVTRAJ";
+/// Last bytes of a finished archive, after the index.
+pub const INDEX_MAGIC: &[u8; 8] = b"GRAVINDX";
+/// Version written by `ArchiveWriter`. Newer archives are rejected.
+pub const VERSION: u16 = 1;
+/// Frames per chunk unless set otherwise.
+pub const CHUNK_FRAMES: usize = 256;
+
+/// How positions and velocities are stored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Precision {
+    /// Bit-exact: every value XORed with its extrapolation from the previous three of its column,
+    /// leading and trailing zero bytes dropped. Along smooth orbits the two agree in sign, exponent
+    /// and the top of the mantissa.
+    Exact,
+    /// Rounded to multiples of `position` and `velocity` (in the system's units), stored as the
+    /// difference to the extrapolation from the three previous frames. Errors are at most half a
+    /// step, and on smooth orbits the differences fit in a byte or two.
+    Quantized { position: f64, velocity: f64 },
+}
+
+/// All bodies at one frame of an archive.
+#[derive(Clone, Debug, Default)]
+pub struct Snapshot {
+    pub time: f64,
+    pub units: Units,
+    pub bodies: Vec<CelestialObject>,
+}
+
+/// Where a chunk is and what it covers, one entry of the index.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChunkIndex {
+    pub offset: u64, // of the chunk's length prefix
+    pub first_frame: u64,
+    pub frames: u64,
+    pub start: f64, // time of the first frame
+    pub end: f64, // time of the last frame
+}
+
+/// Columns of one body in a chunk, before encoding.
+#[derive(Clone, Debug, Default)]
+struct Columns {
+    body: CelestialObject, // name, mass, radius, kind and colour; constant over a chunk
+    x: Vec<f64>,
+    y: Vec<f64>,
+    vx: Vec<f64>,
+    vy: Vec<f64>,
+}
+
+/// Writes a trajectory archive, chunk by chunk.
+///
+/// Frames are buffered and written as a chunk every `chunk_frames` frames, or earlier when the set
+/// of bodies changes (a collision, a body added) as every chunk has a fixed list of bodies. Each
+/// chunk stores its times and, per body, the columns x, y, vx and vy encoded with `precision`
+/// and compressed separately, so a reader decodes only the bodies it asks for. `finish`, also
+/// called on drop, writes the last chunk and an index of all chunks at the end of the file.
+/// Readers of an archive without an index, e.g. from a run that was killed, rebuild it by
+/// walking the chunks and lose only the frames that were still buffered.
+pub struct ArchiveWriter {
+    out: BufWriter<File>,
+    pub precision: Precision,
+    pub chunk_frames: usize,
+    offset: u64,
+    frames: u64,
+    index: Vec<ChunkIndex>,
+    times: Vec<f64>,
+    units: Units,
+    columns: Vec<Columns>,
+    finished: bool,
+}
+
+impl ArchiveWriter {
+    pub fn create(path: impl AsRef<Path>, precision: Precision) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        let mut header = Vec::new();
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        out.write_all(&header)?;
+        Ok(ArchiveWriter {
+            out,
+            precision,
+            chunk_frames: CHUNK_FRAMES,
+            offset: header.len() as u64,
+            frames: 0,
+            index: Vec::new(),
+            times: Vec::new(),
+            units: Units::SI,
+            columns: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Appends the state of `bodies` at `time`, given in `units`; fails once the archive is finished.
+    pub fn push(&mut self, bodies: &[CelestialObject], time: f64, units: Units) -> io::Result<()> {
+        if self.finished {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the trajectory archive is already finished"));
+        }
+        let same = units == self.units
+            && bodies.len() == self.columns.len()
+            && bodies.iter().zip(&self.columns).all(|(body, columns)| same_body(body, &columns.body));
+        if !same {
+            self.write_chunk()?;
+            self.units = units;
+            self.columns = bodies.iter()
+                .map(|body| Columns { body: body.clone(), ..Default::default() })
+                .collect();
+        }
+        self.times.push(time);
+        for (body, columns) in bodies.iter().zip(&mut self.columns) {
+            columns.x.push(body.position.x);
+            columns.y.push(body.position.y);
+            columns.vx.push(body.velocity.x);
+            columns.vy.push(body.velocity.y);
+        }
+        self.frames += 1;
+        if self.times.len() >= self.chunk_frames {
+            self.write_chunk()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered frames as a chunk, if there are any.
+    fn write_chunk(&mut self) -> io::Result<()> {
+        if self.times.is_empty() {
+            return Ok(());
+        }
+        let frames = self.times.len();
+        let mut payload = Vec::new();
+        put_u64(&mut payload, frames as u64);
+        put_u64(&mut payload, self.columns.len() as u64);
+        put_u64(&mut payload, self.frames - frames as u64);
+        for value in [self.units.length, self.units.time, self.units.mass] {
+            put_f64(&mut payload, value);
+        }
+        put_precision(&mut payload, self.precision);
+        put_block(&mut payload, &compress(&encode_exact(&self.times)));
+        for columns in &self.columns {
+            put_body(&mut payload, &columns.body);
+        }
+        let blocks: Vec<Vec<u8>> = self.columns.iter()
+            .map(|columns| {
+                let mut encoded = Vec::new();
+                let (position, velocity) = match self.precision {
+                    Precision::Exact => (None, None),
+                    Precision::Quantized { position, velocity } => (Some(position), Some(velocity)),
+                };
+                for (column, step) in [(&columns.x, position), (&columns.y, position), (&columns.vx, velocity), (&columns.vy, velocity)] {
+                    encoded.extend(match step {
+                        None => encode_exact(column),
+                        Some(step) => encode_quantized(column, step),
+                    });
+                }
+                compress(&encoded)
+            })
+            .collect();
+        for block in &blocks {
+            put_u64(&mut payload, block.len() as u64);
+        }
+        for block in &blocks {
+            payload.extend_from_slice(block);
+        }
+
+        self.out.write_all(&(payload.len() as u64).to_le_bytes())?;
+        self.out.write_all(&payload)?;
+        self.index.push(ChunkIndex {
+            offset: self.offset,
+            first_frame: self.frames - frames as u64,
+            frames: frames as u64,
+            start: self.times[0],
+            end: self.times[frames - 1],
+        });
+        self.offset += 8 + payload.len() as u64;
+        self.times.clear();
+        for columns in &mut self.columns {
+            columns.x.clear();
+            columns.y.clear();
+            columns.vx.clear();
+            columns.vy.clear();
+        }
+        Ok(())
+    }
+
+    /// Writes the buffered frames to disk as a chunk, without ending the archive.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.write_chunk()?;
+        self.out.flush()
+    }
+
+    /// Writes the last chunk and the index. Nothing can be pushed afterwards.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.write_chunk()?;
+        let mut index = Vec::new();
+        put_u64(&mut index, self.index.len() as u64);
+        for chunk in &self.index {
+            put_u64(&mut index, chunk.offset);
+            put_u64(&mut index, chunk.first_frame);
+            put_u64(&mut index, chunk.frames);
+            put_f64(&mut index, chunk.start);
+            put_f64(&mut index, chunk.end);
+        }
+        put_u64(&mut index, self.offset);
+        index.extend_from_slice(INDEX_MAGIC);
+        self.out.write_all(&index)?;
+        self.out.flush()?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl Drop for ArchiveWriter {
+    fn drop(&mut self) {
+        if let Err(error) = self.finish() {
+            eprintln!("Cannot finish the trajectory archive: {error}");
+        }
+    }
+}
+
+/// Whether two states are of the same body as far as an archive chunk is concerned.
+fn same_body(a: &CelestialObject, b: &CelestialObject) -> bool {
+    a.name == b.name && a.mass == b.mass && a.radius == b.radius && a.kind == b.kind && a.colour == b.colour
+}
+
+/// Random access to a trajectory archive written by `ArchiveWriter`.
+///
+/// Opening reads only the index. `frame` decodes the one chunk holding the frame and keeps it, so
+/// playing frames in order decodes every chunk once; `body` decodes only that body's columns of
+/// the chunks overlapping the time range.
+pub struct ArchiveReader {
+    file: File,
+    pub chunks: Vec<ChunkIndex>,
+    cache: Option<(usize, Vec<Snapshot>)>, // the last chunk decoded by `frame`
+}
+
+/// A chunk read from disk, with its bodies' blocks still compressed.
+struct Chunk {
+    first_frame: u64,
+    times: Vec<f64>,
+    units: Units,
+    precision: Precision,
+    bodies: Vec<CelestialObject>,
+    blocks: Vec<Vec<u8>>,
+}
+
+impl Chunk {
+    /// Positions and velocities of body `i` at every frame of the chunk.
+    fn columns(&self, i: usize) -> io::Result<[Vec<f64>; 4]> {
+        let frames = self.times.len();
+        let bytes = decompress(&self.blocks[i])?;
+        let mut reader = Reader { bytes: &bytes };
+        let (position, velocity) = match self.precision {
+            Precision::Exact => (None, None),
+            Precision::Quantized { position, velocity } => (Some(position), Some(velocity)),
+        };
+        let mut column = |step: Option<f64>| match step {
+            None => decode_exact(&mut reader, frames),
+            Some(step) => decode_quantized(&mut reader, frames, step),
+        };
+        Ok([column(position)?, column(position)?, column(velocity)?, column(velocity)?])
+    }
+
+    /// Body `i` at frame `k` of the chunk, from its decoded columns.
+    fn body(&self, i: usize, columns: &[Vec<f64>; 4], k: usize) -> CelestialObject {
+        let mut body = self.bodies[i].clone();
+        body.position = Point2::new(columns[0][k], columns[1][k]);
+        body.prevposition = body.position;
+        body.velocity = Vector2::new(columns[2][k], columns[3][k]);
+        body
+    }
+}
+
+impl ArchiveReader {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let mut header = [0u8; 10];
+        file.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(invalid("not a trajectory archive"));
+        }
+        let version = u16::from_le_bytes([header[8], header[9]]);
+        if version > VERSION {
+            return Err(invalid(format!("archive version {version} is newer than this build, which reads up to {VERSION}")));
+        }
+        let chunks = match ArchiveReader::read_index(&mut file)? {
+            Some(chunks) => chunks,
+            None => ArchiveReader::scan(&mut file)?,
+        };
+        Ok(ArchiveReader { file, chunks, cache: None })
+    }
+
+    /// The index at the end of a finished archive, None if there is none.
+    fn read_index(file: &mut File) -> io::Result<Option<Vec<ChunkIndex>>> {
+        let length = file.seek(SeekFrom::End(0))?;
+        if length < 10 + 16 {
+            return Ok(None);
+        }
+        let mut footer = [0u8; 16];
+        file.seek(SeekFrom::End(-16))?;
+        file.read_exact(&mut footer)?;
+        if &footer[8..] != INDEX_MAGIC {
+            return Ok(None);
+        }
+        let offset = u64::from_le_bytes(footer[..8].try_into().unwrap());
+        if offset > length - 16 {
+            return Ok(None);
+        }
+        let mut bytes = vec![0u8; (length - 16 - offset) as usize];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut bytes)?;
+        let mut reader = Reader { bytes: &bytes };
+        let count = reader.u64()?;
+        if count > reader.bytes.len() as u64 / 40 {
+            return Err(invalid("corrupt index"));
+        }
+        let chunks: Vec<ChunkIndex> = (0..count)
+            .map(|_| Ok(ChunkIndex {
+                offset: reader.u64()?,
+                first_frame: reader.u64()?,
+                frames: reader.u64()?,
+                start: reader.f64()?,
+                end: reader.f64()?,
+            }))
+            .collect::<io::Result<_>>()?;
+        if chunks.iter().any(|chunk| chunk.offset < 10 || chunk.offset.saturating_add(8) > offset) {
+            return Err(invalid("index points outside the chunks"));
+        }
+        Ok(Some(chunks))
+    }
+
+    /// Rebuilds the index by walking the chunks, stopping at the first incomplete one.
+    fn scan(file: &mut File) -> io::Result<Vec<ChunkIndex>> {
+        let length = file.seek(SeekFrom::End(0))?;
+        let mut chunks = Vec::new();
+        let mut offset = 10;
+        while offset + 8 <= length {
+            let Ok(chunk) = ArchiveReader::read_chunk_at(file, offset) else { break };
+            let size = 8 + ArchiveReader::payload_length(file, offset)?; // checked against the file by `read_chunk_at`
+            let frames = chunk.times.len() as u64;
+            chunks.push(ChunkIndex { offset, first_frame: chunk.first_frame, frames, start: chunk.times[0], end: chunk.times[chunk.times.len() - 1] });
+            offset += size;
+        }
+        Ok(chunks)
+    }
+
+    fn payload_length(file: &mut File, offset: u64) -> io::Result<u64> {
+        let mut length = [0u8; 8];
+        file.seek(SeekFrom::Start(offset))?;
+        file.read_exact(&mut length)?;
+        Ok(u64::from_le_bytes(length))
+    }
+
+    fn read_chunk_at(file: &mut File, offset: u64) -> io::Result<Chunk> {
+        let size = file.metadata()?.len();
+        let length = ArchiveReader::payload_length(file, offset)?;
+        if length > size.saturating_sub(offset + 8) {
+            return Err(invalid(format!("chunk at {offset} runs past the end of the archive")));
+        }
+        let mut payload = vec![0u8; length as usize];
+        file.read_exact(&mut payload)?;
+        let mut reader = Reader { bytes: &payload };
+        let frames = reader.u64()? as usize;
+        let count = reader.u64()? as usize;
+        let first_frame = reader.u64()?;
+        let units = Units { length: reader.f64()?, time: reader.f64()?, mass: reader.f64()? };
+        let precision = read_precision(&mut reader)?;
+        let times = decode_exact(&mut Reader { bytes: &decompress(reader.block()?)? }, frames)?;
+        if times.is_empty() {
+            return Err(invalid("empty chunk"));
+        }
+        let bodies = (0..count).map(|_| read_body(&mut reader)).collect::<io::Result<Vec<_>>>()?;
+        let lengths = (0..count).map(|_| reader.u64()).collect::<io::Result<Vec<_>>>()?;
+        let blocks = lengths.iter()
+            .map(|&length| reader.take(length as usize).map(<[u8]>::to_vec))
+            .collect::<io::Result<_>>()?;
+        Ok(Chunk { first_frame, times, units, precision, bodies, blocks })
+    }
+
+    fn read_chunk(&mut self, chunk: usize) -> io::Result<Chunk> {
+        ArchiveReader::read_chunk_at(&mut self.file, self.chunks[chunk].offset)
+    }
+
+    /// Number of frames in the archive.
+    pub fn frames(&self) -> u64 {
+        self.chunks.last().map_or(0, |last| last.first_frame + last.frames)
+    }
+
+    /// Times of the first and last frames, None for an empty archive.
+    pub fn time_range(&self) -> Option<(f64, f64)> {
+        Some((self.chunks.first()?.start, self.chunks.last()?.end))
+    }
+
+    /// Every body at frame `k`, counted from 0.
+    pub fn frame(&mut self, k: u64) -> io::Result<Snapshot> {
+        let chunk = self.chunks.iter()
+            .position(|chunk| k < chunk.first_frame + chunk.frames)
+            .filter(|_| k < self.frames())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no frame {k} in the archive")))?;
+        if self.cache.as_ref().map(|(cached, _)| *cached) != Some(chunk) {
+            let data = self.read_chunk(chunk)?;
+            let columns = (0..data.bodies.len()).map(|i| data.columns(i)).collect::<io::Result<Vec<_>>>()?;
+            let snapshots = (0..data.times.len())
+                .map(|frame| Snapshot {
+                    time: data.times[frame],
+                    units: data.units,
+                    bodies: (0..data.bodies.len()).map(|i| data.body(i, &columns[i], frame)).collect(),
+                })
+                .collect();
+            self.cache = Some((chunk, snapshots));
+        }
+        let (_, snapshots) = self.cache.as_ref().unwrap();
+        Ok(snapshots[(k - self.chunks[chunk].first_frame) as usize].clone())
+    }
+
+    /// The last frame at or before `time`, the first frame if `time` is earlier than all.
+    pub fn frame_at(&mut self, time: f64) -> io::Result<u64> {
+        let chunk = match self.chunks.iter().rposition(|chunk| chunk.start <= time) {
+            Some(chunk) => chunk,
+            None => return Ok(0),
+        };
+        let times = self.read_chunk(chunk)?.times;
+        let within = times.partition_point(|&t| t <= time).max(1) - 1;
+        Ok(self.chunks[chunk].first_frame + within as u64)
+    }
+
+    /// The named body at every frame from `start` to `end` inclusive, with their times.
+    pub fn body(&mut self, name: &str, start: f64, end: f64) -> io::Result<Vec<(f64, CelestialObject)>> {
+        let mut samples = Vec::new();
+        for chunk in 0..self.chunks.len() {
+            if self.chunks[chunk].end < start || self.chunks[chunk].start > end {
+                continue;
+            }
+            let data = self.read_chunk(chunk)?;
+            let Some(i) = data.bodies.iter().position(|body| body.name == name) else { continue };
+            let columns = data.columns(i)?;
+            for (k, &time) in data.times.iter().enumerate() {
+                if time >= start && time <= end {
+                    samples.push((time, data.body(i, &columns, k)));
+                }
+            }
+        }
+        Ok(samples)
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn put_u64(bytes: &mut Vec<u8>, value: u64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_f64(bytes: &mut Vec<u8>, value: f64) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_block(bytes: &mut Vec<u8>, block: &[u8]) {
+    put_u64(bytes, block.len() as u64);
+    bytes.extend_from_slice(block);
+}
+
+fn put_precision(bytes: &mut Vec<u8>, precision: Precision) {
+    match precision {
+        Precision::Exact => bytes.push(0),
+        Precision::Quantized { position, velocity } => {
+            bytes.push(1);
+            put_f64(bytes, position);
+            put_f64(bytes, velocity);
+        }
+    }
+}
+
+fn read_precision(reader: &mut Reader) -> io::Result<Precision> {
+    match reader.u8()? {
+        0 => Ok(Precision::Exact),
+        1 => Ok(Precision::Quantized { position: reader.f64()?, velocity: reader.f64()? }),
+        other => Err(invalid(format!("unknown precision {other}"))),
+    }
+}
+
+fn put_body(bytes: &mut Vec<u8>, body: &CelestialObject) {
+    put_block(bytes, body.name.as_bytes());
+    put_f64(bytes, body.mass);
+    put_f64(bytes, body.radius);
+    bytes.push(match body.kind {
+        BodyKind::Regular => 0,
+        BodyKind::BlackHole => 1,
+        BodyKind::TestParticle => 2,
+    });
+    match body.colour {
+        Some(colour) => {
+            bytes.push(1);
+            bytes.extend_from_slice(&colour);
+        }
+        None => bytes.push(0),
+    }
+}
+
+fn read_body(reader: &mut Reader) -> io::Result<CelestialObject> {
+    let name = String::from_utf8(reader.block()?.to_vec()).map_err(|error| invalid(error.to_string()))?;
+    let origin = Point2::new(0.0, 0.0);
+    let mut body = CelestialObject::new(name, reader.f64()?, origin, Vector2::new(0.0, 0.0), Vector2::new(0.0, 0.0), origin);
+    body.radius = reader.f64()?;
+    body.kind = match reader.u8()? {
+        0 => BodyKind::Regular,
+        1 => BodyKind::BlackHole,
+        2 => BodyKind::TestParticle,
+        other => return Err(invalid(format!("unknown body kind {other}"))),
+    };
+    body.colour = match reader.u8()? {
+        0 => None,
+        _ => Some([reader.u8()?, reader.u8()?, reader.u8()?]),
+    };
+    Ok(body)
+}
+
+/// Decoder of the little-endian values and blocks written above.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "archive is truncated"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn block(&mut self) -> io::Result<&'a [u8]> {
+        let length = self.u64()? as usize;
+        self.take(length)
+    }
+
+    /// LEB128 unsigned varint.
+    fn varint(&mut self) -> io::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("varint is too long"))
+    }
+}
+
+/// XOR with the extrapolated value, then a control byte with the number of leading (high nibble) and
+/// trailing (low nibble) zero bytes of the XOR, then the bytes in between, most significant first.
+fn encode_exact(values: &[f64]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(values.len() * 4);
+    for (i, value) in values.iter().enumerate() {
+        let xor = value.to_bits() ^ extrapolate(&values[..i]).to_bits();
+        if xor == 0 {
+            bytes.push(0x80);
+            continue;
+        }
+        let (leading, trailing) = (xor.leading_zeros() / 8, xor.trailing_zeros() / 8);
+        bytes.push((leading << 4 | trailing) as u8);
+        bytes.extend_from_slice(&xor.to_be_bytes()[leading as usize..(8 - trailing) as usize]);
+    }
+    bytes
+}
+
+fn decode_exact(reader: &mut Reader, count: usize) -> io::Result<Vec<f64>> {
+    if count > reader.bytes.len() {
+        return Err(invalid("corrupt column")); // every value takes at least a byte
+    }
+    let mut values = Vec::with_capacity(count);
+    for _ in 0..count {
+        let control = reader.u8()?;
+        let (leading, trailing) = ((control >> 4) as usize, (control & 0x0f) as usize);
+        if leading + trailing > 8 {
+            return Err(invalid("corrupt column"));
+        }
+        let mut xor = [0u8; 8];
+        xor[leading..8 - trailing].copy_from_slice(reader.take(8 - leading - trailing)?);
+        values.push(f64::from_bits(extrapolate(&values).to_bits() ^ u64::from_be_bytes(xor)));
+    }
+    Ok(values)
+}
+
+/// Rounds to multiples of `step` and stores the zigzag varint of the difference to the prediction
+/// of `predict`, which is small along smooth orbits.
+fn encode_quantized(values: &[f64], step: f64) -> Vec<u8> {
+    let quantized: Vec<i64> = values.iter().map(|value| (value / step).round() as i64).collect();
+    let mut bytes = Vec::with_capacity(values.len() * 2);
+    for (i, &q) in quantized.iter().enumerate() {
+        let residual = q.wrapping_sub(predict(&quantized[..i]));
+        let mut zigzag = ((residual << 1) ^ (residual >> 63)) as u64;
+        while zigzag >= 0x80 {
+            bytes.push((zigzag as u8 & 0x7f) | 0x80);
+            zigzag >>= 7;
+        }
+        bytes.push(zigzag as u8);
+    }
+    bytes
+}
+
+fn decode_quantized(reader: &mut Reader, count: usize, step: f64) -> io::Result<Vec<f64>> {
+    if count > reader.bytes.len() {
+        return Err(invalid("corrupt column"));
+    }
+    let mut quantized = Vec::with_capacity(count);
+    for _ in 0..count {
+        let zigzag = reader.varint()?;
+        let residual = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+        quantized.push(predict(&quantized).wrapping_add(residual));
+    }
+    Ok(quantized.into_iter().map(|q| q as f64 * step).collect())
+}
+
+/// Quadratic extrapolation 3v[i-1] - 3v[i-2] + v[i-3] of the next value of an exact column, linear
+/// or constant at its start. Pure arithmetic on the previous values, so decoding repeats it exactly.
+fn extrapolate(previous: &[f64]) -> f64 {
+    match previous {
+        [] => 0.0,
+        [only] => *only,
+        [first, second] => 2.0 * second - first,
+        [.., a, b, c] => 3.0 * c - 3.0 * b + a,
+    }
+}
+
+/// `extrapolate` for quantized columns, in wrapping integer arithmetic.
+fn predict(previous: &[i64]) -> i64 {
+    match previous {
+        [] => 0,
+        [only] => *only,
+        [first, second] => second.wrapping_mul(2).wrapping_sub(*first),
+        [.., a, b, c] => c.wrapping_mul(3).wrapping_sub(b.wrapping_mul(3)).wrapping_add(*a),
+    }
+}
+
+/// LZ77 compression in the LZ4 block layout: sequences of a token (literal count in the high
+/// nibble, match length - 4 in the low one, 15 meaning more length bytes follow), the literals,
+/// and a 2-byte offset back to the match. The last sequence has literals only.
+fn compress(input: &[u8]) -> Vec<u8> {
+    const MIN_MATCH: usize = 4;
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    let mut table = vec![usize::MAX; 1 << 12];
+    let (mut anchor, mut i) = (0, 0);
+    while i + MIN_MATCH <= input.len() {
+        let key = u32::from_le_bytes(input[i..i + 4].try_into().unwrap());
+        let slot = (key.wrapping_mul(2_654_435_761) >> 20) as usize;
+        let candidate = std::mem::replace(&mut table[slot], i);
+        if candidate != usize::MAX && i - candidate <= u16::MAX as usize && input[candidate..candidate + 4] == input[i..i + 4] {
+            let mut length = MIN_MATCH;
+            while i + length < input.len() && input[candidate + length] == input[i + length] {
+                length += 1;
+            }
+            write_sequence(&mut out, &input[anchor..i], Some(((i - candidate) as u16, length - MIN_MATCH)));
+            i += length;
+            anchor = i;
+        } else {
+            i += 1;
+        }
+    }
+    write_sequence(&mut out, &input[anchor..], None);
+    out
+}
+
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(u16, usize)>) {
+    let extra = matched.map_or(0, |(_, extra)| extra);
+    out.push((literals.len().min(15) << 4 | extra.min(15)) as u8);
+    write_length(out, literals.len());
+    out.extend_from_slice(literals);
+    if let Some((offset, extra)) = matched {
+        out.extend_from_slice(&offset.to_le_bytes());
+        write_length(out, extra);
+    }
+}
+
+/// The part of a length beyond the 15 that fits its nibble, as bytes of 255 and a remainder.
+fn write_length(out: &mut Vec<u8>, length: usize) {
+    if length < 15 {
+        return;
+    }
+    let mut rest = length - 15;
+    while rest >= 255 {
+        out.push(255);
+        rest -= 255;
+    }
+    out.push(rest as u8);
+}
+
+fn decompress(input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut reader = Reader { bytes: input };
+    let mut out = Vec::with_capacity(input.len() * 2);
+    let length = |reader: &mut Reader, nibble: u8| -> io::Result<usize> {
+        let mut length = nibble as usize;
+        if nibble == 15 {
+            loop {
+                let byte = reader.u8()?;
+                length += byte as usize;
+                if byte != 255 {
+                    break;
+                }
+            }
+        }
+        Ok(length)
+    };
+    while !reader.bytes.is_empty() {
+        let token = reader.u8()?;
+        let literals = length(&mut reader, token >> 4)?;
+        out.extend_from_slice(reader.take(literals)?);
+        if reader.bytes.is_empty() {
+            break;
+        }
+        let offset = u16::from_le_bytes(reader.take(2)?.try_into().unwrap()) as usize;
+        if offset == 0 || offset > out.len() {
+            return Err(invalid("corrupt compressed block"));
+        }
+        let matched = length(&mut reader, token & 0x0f)? + 4;
+        let start = out.len() - offset;
+        for k in 0..matched {
+            out.push(out[start + k]); // byte by byte, the match may overlap what it writes
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::integrator::VelocityVerlet;
+    use crate::space::system::{Simulatable, StarSystem};
+
+    #[test]
+    fn exact_round_trip() {
+        let mut system = StarSystem::solar();
+        let path = std::env::temp_dir().join(format!("gravity-round-trip-{}.traj", std::process::id()));
+        let mut writer = ArchiveWriter::create(&path, Precision::Exact).unwrap();
+        writer.chunk_frames = 16;
+        let mut recorded = Vec::new();
+        for step in 0..100 {
+            if step == 50 {
+                system.bodies.pop(); // a new chunk starts with one body fewer
+                system.generation += 1;
+            }
+            system.run(&VelocityVerlet, 3600.0, 1);
+            writer.push(&system.bodies, system.time, system.units).unwrap();
+            recorded.push((system.time, system.bodies.clone()));
+        }
+        writer.finish().unwrap();
+        assert_eq!(writer.push(&system.bodies, system.time, system.units).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        drop(writer);
+
+        let mut reader = ArchiveReader::open(&path).unwrap();
+        assert_eq!(reader.frames(), 100);
+        for (k, (time, bodies)) in recorded.iter().enumerate() {
+            let snapshot = reader.frame(k as u64).unwrap();
+            assert_eq!(snapshot.time, *time);
+            assert_eq!(snapshot.bodies.len(), bodies.len());
+            for (read, body) in snapshot.bodies.iter().zip(bodies) {
+                assert_eq!((&read.name, read.position, read.velocity), (&body.name, body.position, body.velocity));
+            }
+        }
+        assert_eq!(reader.frame_at(recorded[70].0 + 1.0).unwrap(), 70);
+        let earth = reader.body("Earth", recorded[10].0, recorded[60].0).unwrap();
+        assert_eq!(earth.len(), 51);
+        assert_eq!(earth[40].1.position, recorded[50].1[3].position);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    /// Solar system frames an hour apart, recorded to an archive at `path` with `precision`.
+    fn record(path: &Path, precision: Precision, frames: usize) -> Vec<Vec<CelestialObject>> {
+        let mut system = StarSystem::solar();
+        let mut writer = ArchiveWriter::create(path, precision).unwrap();
+        let mut recorded = Vec::new();
+        for _ in 0..frames {
+            system.run(&VelocityVerlet, 3600.0, 1);
+            writer.push(&system.bodies, system.time, system.units).unwrap();
+            recorded.push(system.bodies.clone());
+        }
+        writer.finish().unwrap();
+        recorded
+    }
+
+    #[test]
+    fn quantization_error_is_bounded() {
+        let (position, velocity) = (1e5, 1e-3); // in meters and meters per second
+        let path = std::env::temp_dir().join(format!("gravity-quantized-{}.traj", std::process::id()));
+        let recorded = record(&path, Precision::Quantized { position, velocity }, 300);
+        let mut reader = ArchiveReader::open(&path).unwrap();
+        for (k, bodies) in recorded.iter().enumerate() {
+            for (read, body) in reader.frame(k as u64).unwrap().bodies.iter().zip(bodies) {
+                let error = (read.position - body.position).abs().max();
+                assert!(error <= 0.5 * position * (1.0 + 1e-9), "{} at frame {k} off by {error} m", body.name);
+                let error = (read.velocity - body.velocity).abs().max();
+                assert!(error <= 0.5 * velocity * (1.0 + 1e-9), "{} at frame {k} off by {error} m/s", body.name);
+            }
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_lengths_are_rejected() {
+        let path = std::env::temp_dir().join(format!("gravity-corrupt-{}.traj", std::process::id()));
+        record(&path, Precision::Exact, 10);
+        let bytes = std::fs::read(&path).unwrap();
+
+        // a chunk claiming to be longer than the file
+        let mut corrupt = bytes.clone();
+        corrupt[10..18].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        std::fs::write(&path, &corrupt).unwrap();
+        let error = ArchiveReader::open(&path).unwrap().frame(0).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        // an index claiming more chunks than it has room for
+        let mut corrupt = bytes.clone();
+        let index = u64::from_le_bytes(bytes[bytes.len() - 16..bytes.len() - 8].try_into().unwrap()) as usize;
+        corrupt[index..index + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &corrupt).unwrap();
+        assert_eq!(ArchiveReader::open(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        // a killed run without an index: the scan stops at the bad chunk
+        let mut corrupt = bytes[..bytes.len() - 16].to_vec();
+        corrupt[10..18].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &corrupt).unwrap();
+        assert_eq!(ArchiveReader::open(&path).unwrap().frames(), 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
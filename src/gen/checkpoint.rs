@@ -0,0 +1,421 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use nalgebra::{Point2, Vector2};
+
+use crate::engine::collision::{Collision, CollisionPolicy, Response};
+use crate::engine::diagnostics::Diagnostics;
+use crate::engine::integrator;
+use crate::engine::physics::QuadTree;
+use crate::engine::solver::SolverSpec;
+use crate::gen::sim::Simulation;
+use crate::space::objects::{BodyKind, CelestialObject};
+use crate::space::units::Units;
+
+/// First bytes of every checkpoint.
+pub const MAGIC: &[u8; 8] = b"GRAVCKPT";
+/// Version written by `checkpoint`. Newer files are rejected, older ones load.
+pub const VERSION: u16 = 1;
+
+// Record tags. Records of unknown tags are skipped, so only add new ones, never renumber.
+const SIMULATION: u16 = 1;
+const DT_HISTORY: u16 = 2;
+const INTEGRATOR: u16 = 3;
+const SOLVER: u16 = 4;
+const BODY: u16 = 5;
+const COLLISION_POLICY: u16 = 6;
+const COLLISION: u16 = 7;
+const BASELINE: u16 = 8;
+
+/// Little-endian encoder for checkpoint records.
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f64(&mut self, value: f64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn vector(&mut self, value: Vector2<f64>) {
+        self.f64(value.x);
+        self.f64(value.y);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u64(value.len() as u64);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    /// Appends a record: its tag, the length of its payload and the payload written by `fill`.
+    fn record(&mut self, tag: u16, fill: impl FnOnce(&mut Writer)) {
+        let mut payload = Writer::default();
+        fill(&mut payload);
+        self.u16(tag);
+        self.u64(payload.bytes.len() as u64);
+        self.bytes.extend(payload.bytes);
+    }
+}
+
+/// Decoder matching `Writer`, failing with `UnexpectedEof` on truncated input.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        if self.bytes.len() < n {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "checkpoint is truncated"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f64(&mut self) -> io::Result<f64> {
+        Ok(f64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn vector(&mut self) -> io::Result<Vector2<f64>> {
+        Ok(Vector2::new(self.f64()?, self.f64()?))
+    }
+
+    fn str(&mut self) -> io::Result<String> {
+        let length = self.u64()? as usize;
+        String::from_utf8(self.take(length)?.to_vec()).map_err(|error| invalid(error.to_string()))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn write_solver(writer: &mut Writer, spec: &SolverSpec) {
+    match spec {
+        SolverSpec::Direct => writer.u8(0),
+        SolverSpec::BarnesHut { theta, capacity } => {
+            writer.u8(1);
+            writer.f64(*theta);
+            writer.u64(*capacity as u64);
+        }
+        SolverSpec::PostNewtonian(newtonian) => {
+            writer.u8(2);
+            write_solver(writer, newtonian);
+        }
+    }
+}
+
+fn read_solver(reader: &mut Reader) -> io::Result<SolverSpec> {
+    match reader.u8()? {
+        0 => Ok(SolverSpec::Direct),
+        1 => Ok(SolverSpec::BarnesHut { theta: reader.f64()?, capacity: reader.u64()? as usize }),
+        2 => Ok(SolverSpec::PostNewtonian(Box::new(read_solver(reader)?))),
+        other => Err(invalid(format!("unknown solver {other}"))),
+    }
+}
+
+fn write_response(writer: &mut Writer, response: &Response) {
+    match response {
+        Response::Merge => writer.u8(0),
+        Response::Bounce { restitution } => {
+            writer.u8(1);
+            writer.f64(*restitution);
+        }
+        Response::Fragment { pieces } => {
+            writer.u8(2);
+            writer.u64(*pieces as u64);
+        }
+        Response::Absorb => writer.u8(3),
+    }
+}
+
+fn read_response(reader: &mut Reader) -> io::Result<Response> {
+    match reader.u8()? {
+        0 => Ok(Response::Merge),
+        1 => Ok(Response::Bounce { restitution: reader.f64()? }),
+        2 => Ok(Response::Fragment { pieces: reader.u64()? as usize }),
+        3 => Ok(Response::Absorb),
+        other => Err(invalid(format!("unknown collision response {other}"))),
+    }
+}
+
+fn write_body(writer: &mut Writer, body: &CelestialObject) {
+    writer.str(&body.name);
+    writer.f64(body.mass);
+    writer.vector(body.position.coords);
+    writer.vector(body.velocity);
+    writer.vector(body.acceleration);
+    writer.vector(body.prevposition.coords);
+    writer.f64(body.radius);
+    writer.u8(match body.kind {
+        BodyKind::Regular => 0,
+        BodyKind::BlackHole => 1,
+        BodyKind::TestParticle => 2,
+    });
+    match body.softening {
+        Some(softening) => {
+            writer.u8(1);
+            writer.f64(softening);
+        }
+        None => writer.u8(0),
+    }
+    match body.colour {
+        Some(colour) => {
+            writer.u8(1);
+            colour.iter().for_each(|&channel| writer.u8(channel));
+        }
+        None => writer.u8(0),
+    }
+}
+
+fn read_body(reader: &mut Reader) -> io::Result<CelestialObject> {
+    let name = reader.str()?;
+    let mass = reader.f64()?;
+    let position = Point2::from(reader.vector()?);
+    let velocity = reader.vector()?;
+    let mut body = CelestialObject::new(name, mass, position, velocity, Vector2::new(0.0, 0.0), position);
+    body.acceleration = reader.vector()?;
+    body.prevposition = Point2::from(reader.vector()?);
+    body.radius = reader.f64()?;
+    body.kind = match reader.u8()? {
+        0 => BodyKind::Regular,
+        1 => BodyKind::BlackHole,
+        2 => BodyKind::TestParticle,
+        other => return Err(invalid(format!("unknown body kind {other}"))),
+    };
+    body.softening = match reader.u8()? {
+        0 => None,
+        _ => Some(reader.f64()?),
+    };
+    body.colour = match reader.u8()? {
+        0 => None,
+        _ => Some([reader.u8()?, reader.u8()?, reader.u8()?]),
+    };
+    Ok(body)
+}
+
+fn write_collision(writer: &mut Writer, collision: &Collision) {
+    writer.f64(collision.time);
+    writer.str(&collision.bodies.0);
+    writer.str(&collision.bodies.1);
+    write_response(writer, &collision.response);
+    writer.u64(collision.products.len() as u64);
+    collision.products.iter().for_each(|product| writer.str(product));
+    writer.vector(collision.position.coords);
+    writer.f64(collision.relative_speed);
+}
+
+fn read_collision(reader: &mut Reader) -> io::Result<Collision> {
+    let time = reader.f64()?;
+    let bodies = (reader.str()?, reader.str()?);
+    let response = read_response(reader)?;
+    let products = (0..reader.u64()?).map(|_| reader.str()).collect::<io::Result<_>>()?;
+    Ok(Collision { time, bodies, response, products, position: Point2::from(reader.vector()?), relative_speed: reader.f64()? })
+}
+
+impl Simulation {
+    /// The full state of the simulation as a binary checkpoint.
+    ///
+    /// A checkpoint starts with `MAGIC` and the format `VERSION`, followed by records, each a tag,
+    /// the length of its payload and the payload, all little-endian. It holds every field of every
+    /// body, the time, the history of time steps, the integrator, the solver and its parameters,
+    /// the collision policy and past collisions, the drift baseline and the seed. Integrators keep
+    /// their state on the bodies (Verlet reuses `acceleration`), so resuming from a checkpoint
+    /// continues the exact same trajectory, bit for bit.
+    ///
+    /// Records of unknown tags are skipped and bytes after the known fields of a record ignored, so
+    /// fields added later go at the end of a record, or in a new one, and must have a default for
+    /// older checkpoints that lack them.
+    pub fn checkpoint(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.u16(VERSION);
+
+        writer.record(SIMULATION, |w| {
+            w.f64(self.time);
+            w.f64(self.time_step);
+            w.f64(self.softening);
+            w.f64(self.units.length);
+            w.f64(self.units.time);
+            w.f64(self.units.mass);
+            w.u64(self.seed);
+        });
+        writer.record(DT_HISTORY, |w| {
+            w.u64(self.dt_history.len() as u64);
+            for &(steps, dt) in &self.dt_history {
+                w.u64(steps);
+                w.f64(dt);
+            }
+        });
+        writer.record(INTEGRATOR, |w| w.str(self.integrator.name()));
+        writer.record(SOLVER, |w| write_solver(w, &self.solver.spec()));
+        for body in &self.bodies {
+            writer.record(BODY, |w| write_body(w, body));
+        }
+        writer.record(COLLISION_POLICY, |w| {
+            write_response(w, &self.collision_policy.default);
+            w.u64(self.collision_policy.pairs.len() as u64);
+            for (a, b, response) in &self.collision_policy.pairs {
+                w.str(a);
+                w.str(b);
+                write_response(w, response);
+            }
+        });
+        for collision in &self.collisions {
+            writer.record(COLLISION, |w| write_collision(w, collision));
+        }
+        if let Some(baseline) = &self.baseline {
+            writer.record(BASELINE, |w| {
+                w.f64(baseline.time);
+                w.f64(baseline.kinetic);
+                w.f64(baseline.potential);
+                w.vector(baseline.momentum);
+                w.f64(baseline.angular_momentum);
+                w.f64(baseline.virial_ratio);
+                w.f64(baseline.momentum_scale);
+                w.f64(baseline.angular_momentum_scale);
+            });
+        }
+        writer.bytes
+    }
+
+    /// Rebuilds a simulation from a checkpoint written by `checkpoint`, ready to `run` on.
+    pub fn restore(bytes: &[u8]) -> io::Result<Self> {
+        let mut reader = Reader { bytes };
+        if reader.take(MAGIC.len()).ok() != Some(MAGIC.as_slice()) {
+            return Err(invalid("not a checkpoint"));
+        }
+        let version = reader.u16()?;
+        if version > VERSION {
+            return Err(invalid(format!("checkpoint version {version} is newer than this build, which reads up to {VERSION}")));
+        }
+
+        let mut simulation = Simulation::new(Vec::new(), SolverSpec::Direct.build(), 0.0, Box::new(integrator::VelocityVerlet));
+        let (mut has_integrator, mut has_solver) = (false, false);
+        while !reader.is_empty() {
+            let tag = reader.u16()?;
+            let length = reader.u64()? as usize;
+            let mut record = Reader { bytes: reader.take(length)? };
+            match tag {
+                SIMULATION => {
+                    simulation.time = record.f64()?;
+                    simulation.time_step = record.f64()?;
+                    simulation.softening = record.f64()?;
+                    simulation.units = Units { length: record.f64()?, time: record.f64()?, mass: record.f64()? };
+                    simulation.seed = record.u64()?;
+                }
+                DT_HISTORY => {
+                    simulation.dt_history = (0..record.u64()?)
+                        .map(|_| Ok((record.u64()?, record.f64()?)))
+                        .collect::<io::Result<_>>()?;
+                }
+                INTEGRATOR => {
+                    let name = record.str()?;
+                    simulation.integrator = integrator::by_name(&name).ok_or_else(|| invalid(format!("unknown integrator {name}")))?;
+                    has_integrator = true;
+                }
+                SOLVER => {
+                    simulation.solver = read_solver(&mut record)?.build();
+                    has_solver = true;
+                }
+                BODY => simulation.bodies.push(read_body(&mut record)?),
+                COLLISION_POLICY => {
+                    let mut policy = CollisionPolicy::new(read_response(&mut record)?);
+                    for _ in 0..record.u64()? {
+                        let (a, b) = (record.str()?, record.str()?);
+                        policy.set(&a, &b, read_response(&mut record)?);
+                    }
+                    simulation.collision_policy = policy;
+                }
+                COLLISION => simulation.collisions.push(read_collision(&mut record)?),
+                BASELINE => {
+                    simulation.baseline = Some(Diagnostics {
+                        time: record.f64()?,
+                        kinetic: record.f64()?,
+                        potential: record.f64()?,
+                        momentum: record.vector()?,
+                        angular_momentum: record.f64()?,
+                        virial_ratio: record.f64()?,
+                        momentum_scale: record.f64()?,
+                        angular_momentum_scale: record.f64()?,
+                    });
+                }
+                _ => {}
+            }
+        }
+        if !has_integrator || !has_solver {
+            return Err(invalid("checkpoint has no integrator or solver"));
+        }
+        simulation.quad_tree = QuadTree::from_bodies(&simulation.bodies, 4);
+        simulation.evaluated = Some(simulation.generation); // the stored accelerations are current
+        Ok(simulation)
+    }
+
+    /// Writes a checkpoint to `path`, through a temporary file so an interrupted write never
+    /// replaces a good checkpoint with a broken one.
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let partial = path.with_extension("partial");
+        fs::write(&partial, self.checkpoint())?;
+        fs::rename(partial, path)
+    }
+
+    pub fn load_checkpoint(path: impl AsRef<Path>) -> io::Result<Self> {
+        Simulation::restore(&fs::read(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine::integrator::VelocityVerlet;
+    use crate::engine::solver::BarnesHut;
+    use crate::gen::sim::Simulation;
+    use crate::space::system::StarSystem;
+
+    #[test]
+    fn resumed_run_is_bit_identical() {
+        let mut simulation = Simulation::new(StarSystem::solar().bodies, Box::new(BarnesHut::new(0.5)), 3600.0, Box::new(VelocityVerlet));
+        simulation.run(500, false);
+
+        let mut resumed = Simulation::restore(&simulation.checkpoint()).unwrap();
+        simulation.run(500, false);
+        resumed.run(500, false);
+
+        assert_eq!(resumed.time, simulation.time);
+        assert_eq!(resumed.bodies.len(), simulation.bodies.len());
+        for (a, b) in simulation.bodies.iter().zip(&resumed.bodies) {
+            assert!(a.position == b.position, "{} at {:?}, resumed at {:?}", a.name, a.position, b.position);
+            assert!(a.velocity == b.velocity, "{} at {:?}, resumed at {:?}", a.name, a.velocity, b.velocity);
+        }
+    }
+}
@@ -1 +1,5 @@
-pub mod sim;
\ No newline at end of file
+pub mod sim;
+pub mod checkpoint;
+pub mod recorder;
+pub mod archive;
+pub mod replay;
\ No newline at end of file
@@ -0,0 +1,249 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::gen::archive::{ArchiveWriter, Precision};
+use crate::space::objects::CelestialObject;
+use crate::space::units::Units;
+
+/// Output format of a `Recorder`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Comma separated values with a header row.
+    #[default]
+    Csv,
+    /// One JSON object per line.
+    Ndjson,
+    /// Chunked, compressed binary archive with random access, see `ArchiveWriter`. Holds positions
+    /// and velocities, which is all a replay needs, but no accelerations or energies.
+    Archive,
+}
+
+impl Format {
+    /// The format matching the extension of `path`: CSV for ".csv", NDJSON for ".ndjson", ".jsonl"
+    /// and ".json", an archive for ".traj".
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            "ndjson" | "jsonl" | "json" => Some(Format::Ndjson),
+            "traj" => Some(Format::Archive),
+            _ => None,
+        }
+    }
+}
+
+/// Writes the bodies' time series for analysis outside the crate, e.g. `observe.py`.
+///
+/// Every recorded step gives one row per body with t, name, x, y, vx, vy, ax, ay and energy, in
+/// the units of the system. A body's energy is its kinetic energy plus half of its potential
+/// energy with every other body, so the energies of all bodies add up to the total. Rows come
+/// every `every` steps, starting with the first state seen, and only for the bodies in `only`
+/// if it is set. Archives keep only the positions and velocities, in far less space.
+pub struct Recorder {
+    out: Output,
+    pub format: Format,
+    pub every: usize, // record every this many steps, 1 for all of them
+    pub only: Option<Vec<String>>, // names of the bodies to record, None for all
+    ticks: usize,
+    header: bool, // whether the CSV header has been written
+}
+
+enum Output {
+    Text(Box<dyn Write>),
+    Archive(ArchiveWriter),
+}
+
+impl Recorder {
+    /// A recorder writing `format`, CSV or NDJSON, to `out`. Archives are made with `archive`, as
+    /// they need a file to seek in; asking for one here fails with `InvalidInput`.
+    pub fn new(out: Box<dyn Write>, format: Format) -> io::Result<Self> {
+        if format == Format::Archive {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "an archive recorder needs an ArchiveWriter"));
+        }
+        Ok(Recorder { out: Output::Text(out), format, every: 1, only: None, ticks: 0, header: false })
+    }
+
+    /// A recorder appending to a trajectory archive.
+    pub fn archive(writer: ArchiveWriter) -> Self {
+        Recorder { out: Output::Archive(writer), format: Format::Archive, every: 1, only: None, ticks: 0, header: false }
+    }
+
+    pub fn stdout(format: Format) -> io::Result<Self> {
+        Recorder::new(Box::new(io::stdout()), format)
+    }
+
+    /// A recorder writing to a new file at `path`, in the format its extension names, CSV otherwise.
+    /// Archives are bit-exact.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        Ok(match Format::from_path(path).unwrap_or_default() {
+            Format::Archive => Recorder::archive(ArchiveWriter::create(path, Precision::Exact)?),
+            format => Recorder::new(Box::new(BufWriter::new(File::create(path)?)), format)?,
+        })
+    }
+
+    /// Keeps one step out of every `every`.
+    pub fn every(mut self, every: usize) -> Self {
+        self.every = every.max(1);
+        self
+    }
+
+    /// Records only the named bodies.
+    pub fn only(mut self, names: &[&str]) -> Self {
+        self.only = Some(names.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    /// Whether any step has been seen yet.
+    pub fn started(&self) -> bool {
+        self.ticks > 0
+    }
+
+    /// Counts a step and tells whether it is one to record.
+    pub fn tick(&mut self) -> bool {
+        let due = self.ticks.is_multiple_of(self.every.max(1));
+        self.ticks += 1;
+        due
+    }
+
+    /// Writes one row per (selected) body for the state at `time`, with `bodies` given in `units`
+    /// and `softening` the system's Plummer length.
+    pub fn record(&mut self, bodies: &[CelestialObject], time: f64, units: Units, softening: f64) -> io::Result<()> {
+        let out = match &mut self.out {
+            Output::Text(out) => out,
+            Output::Archive(writer) => {
+                return match &self.only {
+                    Some(only) => {
+                        let selected: Vec<CelestialObject> = bodies.iter().filter(|body| only.contains(&body.name)).cloned().collect();
+                        writer.push(&selected, time, units)
+                    }
+                    None => writer.push(bodies, time, units),
+                };
+            }
+        };
+        if self.format == Format::Csv && !self.header {
+            writeln!(out, "t,name,x,y,vx,vy,ax,ay,energy")?;
+            self.header = true;
+        }
+        let gravity = units.gravity(softening);
+        for body in bodies {
+            if let Some(only) = &self.only {
+                if !only.contains(&body.name) {
+                    continue;
+                }
+            }
+            let potential = bodies.iter().map(|other| body.get_softened_potential(other, gravity)).sum::<f64>();
+            let energy = body.kinetic_energy() + potential / 2.0;
+            let values = [time, body.position.x, body.position.y, body.velocity.x, body.velocity.y, body.acceleration.x, body.acceleration.y, energy];
+            match self.format {
+                Format::Csv => {
+                    let [t, x, y, vx, vy, ax, ay, energy] = values.map(|value| format!("{value:e}"));
+                    writeln!(out, "{t},{},{x},{y},{vx},{vy},{ax},{ay},{energy}", csv_field(&body.name))?;
+                }
+                Format::Ndjson => {
+                    let [t, x, y, vx, vy, ax, ay, energy] = values.map(json_number);
+                    let name = serde_json::Value::from(body.name.as_str());
+                    writeln!(out, r#"{{"t":{t},"name":{name},"x":{x},"y":{y},"vx":{vx},"vy":{vy},"ax":{ax},"ay":{ay},"energy":{energy}}}"#)?;
+                }
+                Format::Archive => unreachable!("archives are written by their own output"),
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.out {
+            Output::Text(out) => out.flush(),
+            Output::Archive(writer) => writer.flush(),
+        }
+    }
+}
+
+/// The name as a CSV field, quoted if it contains a separator, a quote or a line break.
+fn csv_field(name: &str) -> String {
+    if name.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", name.replace('"', "\"\""))
+    } else {
+        name.to_string()
+    }
+}
+
+/// Shortest exact representation, null for the infinities and NaN which JSON has no numbers for.
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{value:e}")
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::engine::integrator::VelocityVerlet;
+    use crate::space::system::{Simulatable, StarSystem};
+
+    /// A buffer the test can read after handing it to a recorder.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// The lines written for ten hour long steps of the solar system, recording the Sun and the
+    /// Earth every third step.
+    fn record(format: Format) -> Vec<String> {
+        let buffer = Shared::default();
+        let recorder = Recorder::new(Box::new(buffer.clone()), format).unwrap().every(3).only(&["Sun", "Earth"]);
+        let mut system = StarSystem::solar().with_recorder(recorder);
+        system.run(&VelocityVerlet, 3600.0, 10);
+        system.recorder.as_mut().unwrap().flush().unwrap();
+        let text = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn csv_rows() {
+        let lines = record(Format::Csv);
+        assert_eq!(lines[0], "t,name,x,y,vx,vy,ax,ay,energy");
+        // the starting state and steps 3, 6 and 9, two bodies each
+        assert_eq!(lines.len(), 1 + 4 * 2);
+        for (k, line) in lines[1..].iter().enumerate() {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert_eq!(fields.len(), 9, "{line}");
+            assert_eq!(fields[0].parse::<f64>().unwrap(), (k / 2 * 3) as f64 * 3600.0);
+            assert_eq!(fields[1], ["Sun", "Earth"][k % 2]);
+            assert!(fields[2..].iter().all(|field| field.parse::<f64>().is_ok()), "{line}");
+        }
+    }
+
+    #[test]
+    fn ndjson_rows() {
+        let lines = record(Format::Ndjson);
+        assert_eq!(lines.len(), 4 * 2);
+        for (k, line) in lines.iter().enumerate() {
+            let row: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert_eq!(row["t"].as_f64(), Some((k / 2 * 3) as f64 * 3600.0));
+            assert_eq!(row["name"], ["Sun", "Earth"][k % 2]);
+            for key in ["x", "y", "vx", "vy", "ax", "ay", "energy"] {
+                assert!(row[key].is_f64(), "{key} in {line}");
+            }
+        }
+    }
+
+    #[test]
+    fn archives_need_their_own_constructor() {
+        let error = Recorder::new(Box::new(io::sink()), Format::Archive).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
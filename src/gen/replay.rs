@@ -0,0 +1,235 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::gen::archive::{ArchiveReader, Snapshot};
+use crate::gen::sim::Simulation;
+
+/// Where a replay takes its frames from.
+pub enum Source {
+    /// A trajectory archive, decoded a chunk at a time as frames are asked for.
+    Archive(ArchiveReader),
+    /// A series of checkpoints, one frame each, restored up front and ordered by time.
+    Checkpoints(Vec<Snapshot>),
+}
+
+/// Plays back a recorded run: frames come out of an archive or a checkpoint series, and nothing
+/// is integrated, so reviewing a long run costs only the decoding.
+///
+/// The playhead is a fractional frame that `advance` moves by `speed` frames per second of wall
+/// time, backwards for a negative speed. Playback pauses when it runs off either end.
+pub struct Replay {
+    source: Source,
+    frames: u64,
+    pub position: f64, // playhead in frames, from 0 to frames - 1
+    pub playing: bool,
+    pub speed: f64, // frames per second of wall time, negative to play backwards
+    current: Option<(u64, Snapshot)>, // the frame last read and its number
+}
+
+/// Playback speeds are kept within this many frames per second either way.
+pub const MAX_SPEED: f64 = 10_000.0;
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+impl Replay {
+    /// Opens a ".traj" archive, a directory of checkpoints or a single checkpoint.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            let mut paths = fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<io::Result<Vec<_>>>()?;
+            paths.retain(|path| path.extension().is_some_and(|extension| extension == "ckpt"));
+            return Replay::checkpoints(&paths);
+        }
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("traj") => Replay::archive(ArchiveReader::open(path)?),
+            _ => Replay::checkpoints(&[path.to_path_buf()]),
+        }
+    }
+
+    pub fn archive(reader: ArchiveReader) -> io::Result<Self> {
+        Replay::new(Source::Archive(reader))
+    }
+
+    /// A replay of the checkpoints at `paths`, in the order of their times whatever the order of the paths.
+    pub fn checkpoints(paths: &[PathBuf]) -> io::Result<Self> {
+        let mut snapshots = paths.iter()
+            .map(|path| {
+                let simulation = Simulation::load_checkpoint(path)
+                    .map_err(|error| io::Error::new(error.kind(), format!("{}: {error}", path.display())))?;
+                Ok(Snapshot { time: simulation.time, units: simulation.units, bodies: simulation.bodies })
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        snapshots.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Replay::new(Source::Checkpoints(snapshots))
+    }
+
+    fn new(source: Source) -> io::Result<Self> {
+        let frames = match &source {
+            Source::Archive(reader) => reader.frames(),
+            Source::Checkpoints(snapshots) => snapshots.len() as u64,
+        };
+        if frames == 0 {
+            return Err(invalid("nothing to replay: no frames recorded"));
+        }
+        Ok(Replay { source, frames, position: 0.0, playing: false, speed: 30.0, current: None })
+    }
+
+    /// Number of frames to play.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// The frame under the playhead.
+    pub fn frame(&self) -> u64 {
+        self.position.round() as u64
+    }
+
+    /// Times of the first and last frames.
+    pub fn time_range(&self) -> (f64, f64) {
+        match &self.source {
+            Source::Archive(reader) => reader.time_range().unwrap_or_default(),
+            Source::Checkpoints(snapshots) => (snapshots[0].time, snapshots[snapshots.len() - 1].time),
+        }
+    }
+
+    /// Plays or pauses. Playing from the end the playhead is heading for starts over from the other end.
+    pub fn toggle(&mut self) {
+        self.playing = !self.playing;
+        let last = (self.frames - 1) as f64;
+        if self.playing && self.speed > 0.0 && self.position >= last {
+            self.position = 0.0;
+        } else if self.playing && self.speed < 0.0 && self.position <= 0.0 {
+            self.position = last;
+        }
+    }
+
+    /// Plays the other way at the same speed.
+    pub fn reverse(&mut self) {
+        self.speed = -self.speed;
+    }
+
+    /// Multiplies the speed by `factor`, keeping its direction, between 1/8 and `MAX_SPEED` frames per second.
+    pub fn scale_speed(&mut self, factor: f64) {
+        self.speed = self.speed.signum() * (self.speed.abs() * factor).clamp(0.125, MAX_SPEED);
+    }
+
+    /// Moves the playhead to `frame`, clamped to the recording.
+    pub fn seek(&mut self, frame: i64) {
+        self.position = frame.clamp(0, self.frames as i64 - 1) as f64;
+    }
+
+    /// Moves the playhead to the last frame at or before `time`.
+    pub fn seek_time(&mut self, time: f64) -> io::Result<()> {
+        let frame = match &mut self.source {
+            Source::Archive(reader) => reader.frame_at(time)?,
+            Source::Checkpoints(snapshots) => snapshots.partition_point(|snapshot| snapshot.time <= time).max(1) as u64 - 1,
+        };
+        self.seek(frame as i64);
+        Ok(())
+    }
+
+    /// Moves the playhead by `frames`, backwards if negative, and pauses to show the frame.
+    pub fn step(&mut self, frames: i64) {
+        self.playing = false;
+        self.seek(self.frame() as i64 + frames);
+    }
+
+    /// Moves the playhead on by `elapsed` wall time if playing and returns the frame under it.
+    pub fn advance(&mut self, elapsed: Duration) -> io::Result<&Snapshot> {
+        if self.playing {
+            let last = (self.frames - 1) as f64;
+            self.position += self.speed * elapsed.as_secs_f64();
+            if self.position < 0.0 || self.position > last {
+                self.position = self.position.clamp(0.0, last);
+                self.playing = false;
+            }
+        }
+        self.snapshot()
+    }
+
+    /// The frame under the playhead, read from the source only when the playhead has moved to another one.
+    pub fn snapshot(&mut self) -> io::Result<&Snapshot> {
+        let frame = self.frame();
+        if self.current.as_ref().map(|(current, _)| *current) != Some(frame) {
+            let snapshot = match &mut self.source {
+                Source::Archive(reader) => reader.frame(frame)?,
+                Source::Checkpoints(snapshots) => snapshots[frame as usize].clone(),
+            };
+            self.current = Some((frame, snapshot));
+        }
+        Ok(&self.current.as_ref().unwrap().1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::integrator::VelocityVerlet;
+    use crate::gen::archive::{ArchiveWriter, Precision};
+    use crate::space::system::{Simulatable, StarSystem};
+
+    #[test]
+    fn replays_an_archive() {
+        let mut system = StarSystem::solar();
+        let path = std::env::temp_dir().join(format!("gravity-replay-{}.traj", std::process::id()));
+        let mut writer = ArchiveWriter::create(&path, Precision::Exact).unwrap();
+        writer.chunk_frames = 16;
+        let mut recorded = Vec::new();
+        for _ in 0..40 {
+            system.run(&VelocityVerlet, 3600.0, 1);
+            writer.push(&system.bodies, system.time, system.units).unwrap();
+            recorded.push((system.time, system.bodies.clone()));
+        }
+        writer.finish().unwrap();
+        drop(writer);
+
+        let mut replay = Replay::open(&path).unwrap();
+        assert_eq!(replay.frames(), 40);
+        assert_eq!(replay.time_range(), (recorded[0].0, recorded[39].0));
+        let check = |replay: &mut Replay, frame: usize| {
+            let snapshot = replay.snapshot().unwrap();
+            assert_eq!(snapshot.time, recorded[frame].0);
+            for (read, body) in snapshot.bodies.iter().zip(&recorded[frame].1) {
+                assert_eq!((&read.name, read.position, read.velocity), (&body.name, body.position, body.velocity));
+            }
+        };
+
+        replay.seek(10);
+        check(&mut replay, 10);
+        replay.seek(-5);
+        assert_eq!(replay.frame(), 0);
+        replay.seek(100);
+        assert_eq!(replay.frame(), 39);
+        replay.seek_time(recorded[20].0 + 1800.0).unwrap();
+        assert_eq!(replay.frame(), 20);
+        check(&mut replay, 20);
+
+        // paused, the playhead stays put
+        replay.advance(Duration::from_secs(1)).unwrap();
+        assert_eq!(replay.frame(), 20);
+
+        replay.playing = true;
+        replay.speed = 10.0;
+        replay.advance(Duration::from_millis(500)).unwrap();
+        assert_eq!(replay.frame(), 25);
+        check(&mut replay, 25);
+
+        // backwards across a chunk boundary, then off the start, where playback pauses
+        replay.reverse();
+        assert_eq!(replay.speed, -10.0);
+        replay.advance(Duration::from_secs(1)).unwrap();
+        assert_eq!(replay.frame(), 15);
+        check(&mut replay, 15);
+        replay.advance(Duration::from_secs(2)).unwrap();
+        assert_eq!(replay.frame(), 0);
+        assert!(!replay.playing);
+        check(&mut replay, 0);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
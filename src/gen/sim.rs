@@ -1,32 +1,95 @@
-use crate::space::objects::{Acceleration, CelestialObject};
-use crate::engine::physics::{QuadTree, Rectangle};
-use crate::space::system;
+use std::path::PathBuf;
+
+use crate::space::objects::{CelestialObject, Gravity};
+use crate::space::units::Units;
+use crate::engine::collision::{self, Collision, CollisionPolicy};
+use crate::engine::diagnostics::Diagnostics;
+use crate::engine::integrator::{self, Integrator};
+use crate::engine::physics::QuadTree;
+use crate::engine::solver::ForceSolver;
+use crate::gen::recorder::Recorder;
 
 pub struct Simulation {
     pub bodies: Vec<CelestialObject>,
     pub quad_tree: QuadTree,
-    pub theta: f64,
+    pub solver: Box<dyn ForceSolver>,
+    pub softening: f64, // Plummer softening length, 0 for exact Newtonian gravity
+    pub units: Units, // units of the bodies, time and softening, SI by default
     pub time_step: f64,
+    pub integrator: Box<dyn Integrator>,
+    pub time: f64,
+    pub collision_policy: CollisionPolicy,
+    pub collisions: Vec<Collision>,
+    pub baseline: Option<Diagnostics>, // measured when the first step is taken
+    pub dt_history: Vec<(u64, f64)>, // runs of (steps, time step) taken so far, oldest first
+    pub seed: u64, // seed for stochastic extensions, kept in checkpoints; the engine itself is deterministic
+    pub checkpoint: Option<(PathBuf, usize)>, // `run` writes a checkpoint to this path every this many steps
+    pub recorder: Option<Recorder>, // writes the bodies' time series as they are stepped
+    pub generation: u64, // bumped whenever bodies are added, removed, merged or split; bump it when editing `bodies`
+    pub evaluated: Option<u64>, // generation the bodies' accelerations were last evaluated for
 }
 
 impl Simulation {
-    pub fn new(bodies: Vec<CelestialObject>, theta: f64, time_step: f64) -> Self {
-        let bounds = Rectangle::new(-1000.0, -1000.0, 2000.0, 2000.0);
-        let quad_tree = QuadTree::new(bounds, 4);
-        Simulation { bodies, quad_tree, theta, time_step }
+    pub fn new(bodies: Vec<CelestialObject>, solver: Box<dyn ForceSolver>, time_step: f64, integrator: Box<dyn Integrator>) -> Self {
+        let quad_tree = QuadTree::from_bodies(&bodies, 4);
+        Simulation {
+            bodies,
+            quad_tree,
+            solver,
+            softening: 0.0,
+            units: Units::SI,
+            time_step,
+            integrator,
+            time: 0.0,
+            collision_policy: CollisionPolicy::default(),
+            collisions: Vec::new(),
+            baseline: None,
+            dt_history: Vec::new(),
+            seed: 0,
+            checkpoint: None,
+            recorder: None,
+            generation: 0,
+            evaluated: None,
+        }
     }
 
     pub fn step(&mut self) {
-        self.quad_tree = QuadTree::new(self.quad_tree.bounds, 4);
-        for body in &self.bodies {
-            self.quad_tree.insert(body.clone());
+        if self.baseline.is_none() {
+            self.baseline = Some(self.diagnostics());
+        }
+        if self.recorder.as_ref().is_some_and(|recorder| !recorder.started()) {
+            self.record(); // the starting state
+        }
+        let (solver, gravity) = (self.solver.as_ref(), self.gravity());
+        if self.evaluated != Some(self.generation) {
+            integrator::refresh(&mut self.bodies, &|bodies| solver.accelerations(bodies, gravity));
+            self.evaluated = Some(self.generation);
+        }
+        self.integrator.step(&mut self.bodies, self.time_step, &|bodies| solver.accelerations(bodies, gravity));
+        self.time += self.time_step;
+        match self.dt_history.last_mut() {
+            Some((steps, dt)) if *dt == self.time_step => *steps += 1,
+            _ => self.dt_history.push((1, self.time_step)),
         }
+        self.quad_tree = QuadTree::from_bodies(&self.bodies, 4);
 
-        for body in &mut self.bodies {
-            let force = self.quad_tree.traverse(&body, self.theta);
-            let acceleration = Acceleration::new(force.x / body.mass, force.y / body.mass);
-            body.velocity += acceleration * self.time_step;
-            body.position += body.velocity * self.time_step;
+        let collisions = collision::resolve(&mut self.bodies, &self.quad_tree, self.time, &self.collision_policy);
+        if !collisions.is_empty() {
+            self.quad_tree = QuadTree::from_bodies(&self.bodies, 4);
+            self.collisions.extend(collisions);
+            self.generation += 1;
+        }
+        self.record();
+    }
+
+    /// Hands the current state to the recorder, if it is due, and drops the recorder if writing fails.
+    fn record(&mut self) {
+        let Some(recorder) = &mut self.recorder else { return };
+        if recorder.tick() {
+            if let Err(error) = recorder.record(&self.bodies, self.time, self.units, self.softening) {
+                eprintln!("Cannot record the trajectory, recording stopped: {error}");
+                self.recorder = None;
+            }
         }
     }
 
@@ -38,6 +101,13 @@ impl Simulation {
             if printable && iter % 10 == 0 {
                 self.state();
             }
+            if let Some((path, every)) = &self.checkpoint {
+                if *every > 0 && iter % every == 0 {
+                    if let Err(error) = self.save_checkpoint(path) {
+                        eprintln!("Cannot write checkpoint {}: {}", path.display(), error);
+                    }
+                }
+            }
         }
     }
 
@@ -45,5 +115,26 @@ impl Simulation {
         for (i, body) in self.bodies.iter().enumerate() {
             println!("Body {}: Position ({}, {}), Velocity ({}, {})", i, body.position.x, body.position.y, body.velocity.x, body.velocity.y);
         }
+
+        let diagnostics = self.diagnostics();
+        println!("t = {}: Energy {:e}, Virial ratio {:.4}", self.time, diagnostics.energy(), diagnostics.virial_ratio);
+        if let Some(baseline) = &self.baseline {
+            let drift = diagnostics.drift(baseline);
+            println!("Drift: energy {:e}, momentum {:e}, angular momentum {:e}", drift.energy, drift.momentum, drift.angular_momentum);
+        }
+    }
+
+    /// G and c in the simulation's units, with its softening.
+    pub fn gravity(&self) -> Gravity {
+        self.units.gravity(self.softening)
+    }
+
+    /// Conserved quantities of the current state; the potential comes from the tree beyond 1000 bodies.
+    pub fn diagnostics(&self) -> Diagnostics {
+        if self.bodies.len() > 1000 {
+            Diagnostics::measure_tree(&self.bodies, self.time, self.gravity(), 0.5)
+        } else {
+            Diagnostics::measure(&self.bodies, self.time, self.gravity())
+        }
     }
 }
\ No newline at end of file
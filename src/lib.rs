@@ -0,0 +1,4 @@
+pub mod engine;
+pub mod gen;
+pub mod space;
+pub mod term;
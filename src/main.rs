@@ -1,13 +1,221 @@
 use nalgebra::{Point2, Vector2};
 
-mod engine;
-mod gen;
-mod space;
+use gravity::engine::integrator::{Integrator, VelocityVerlet};
+use gravity::engine::solver::BarnesHut;
+use gravity::space::objects::CelestialObject;
+use gravity::space::scenario::Scenario;
+use gravity::space::system::{Simulatable, StarSystem};
+use gravity::gen::archive::ArchiveReader;
+use gravity::gen::recorder::{Format, Recorder};
+use gravity::gen::replay::Replay;
+use gravity::gen::sim::Simulation;
+use gravity::term::terminal::TerminalInterface;
 
-use crate::space::objects::CelestialObject;
-use crate::gen::sim::Simulation;
+fn load(path: &str) -> Scenario {
+    match Scenario::load(path) {
+        Ok(scenario) => scenario,
+        Err(error) => {
+            eprintln!("Cannot load scenario {path}: {error}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Runs the scenario file at `path` for `iterations` steps and prints the events it watches for as
+/// they happen, then where it ends up; writes the end state as a scenario to `save` if given.
+fn run_scenario(path: &str, iterations: usize, recorder: Option<Recorder>, save: Option<&str>, quiet: bool) {
+    let mut scenario = load(path);
+    scenario.system.recorder = recorder;
+    if !quiet {
+        scenario.system.on_event(|event| println!("{event}"));
+    }
+    scenario.system.run(scenario.integrator.as_ref(), scenario.dt, iterations);
+    flush(&mut scenario.system.recorder);
+    if let Some(save) = save {
+        if let Err(error) = scenario.system.save(save, scenario.integrator.as_ref(), scenario.dt) {
+            eprintln!("Cannot write scenario {save}: {error}");
+        }
+    }
+    if !quiet {
+        scenario.system.state();
+    }
+}
+
+/// Writes out what the recorder still buffers, so the recording is complete once the run is over.
+fn flush(recorder: &mut Option<Recorder>) {
+    if let Some(Err(error)) = recorder.as_mut().map(Recorder::flush) {
+        eprintln!("Cannot write the recording: {error}");
+    }
+}
+
+/// The file asked for with `--save <file.json>`, if any; removes the option from `args`.
+fn save_from(args: &mut Vec<String>) -> Option<String> {
+    let at = args.iter().position(|arg| arg == "--save")?;
+    let path = args.get(at + 1).cloned();
+    args.drain(at..(at + 2).min(args.len()));
+    path
+}
+
+/// Continues the run checkpointed at `path` for `iterations` steps, checkpointing back to it as it goes.
+fn resume(path: &str, iterations: usize, recorder: Option<Recorder>, quiet: bool) {
+    let mut simulation = match Simulation::load_checkpoint(path) {
+        Ok(simulation) => simulation,
+        Err(error) => {
+            eprintln!("Cannot load checkpoint {path}: {error}");
+            std::process::exit(1);
+        }
+    };
+    simulation.checkpoint = Some((path.into(), 100));
+    simulation.recorder = recorder;
+    simulation.run(iterations, false);
+    flush(&mut simulation.recorder);
+    if let Err(error) = simulation.save_checkpoint(path) {
+        eprintln!("Cannot write checkpoint {path}: {error}");
+    }
+    if !quiet {
+        simulation.state();
+    }
+}
+
+/// Shows the scenario at `path`, or the solar system without one, in the terminal and steps it as it is watched.
+fn view(path: Option<&str>, recorder: Option<Recorder>) {
+    let (mut system, integrator, dt) = match path {
+        Some(path) => {
+            let scenario = load(path);
+            (scenario.system, scenario.integrator, scenario.dt)
+        }
+        None => (StarSystem::solar(), Box::new(VelocityVerlet) as Box<dyn Integrator>, 3600.0),
+    };
+    system.recorder = recorder;
+    let result = TerminalInterface::new().and_then(|mut interface| {
+        let result = interface.run(&mut system, integrator.as_ref(), dt);
+        interface.restore()?;
+        result
+    });
+    flush(&mut system.recorder);
+    if let Err(error) = result {
+        eprintln!("Terminal error: {error}");
+        std::process::exit(1);
+    }
+}
+
+/// Prints the named body's states between `start` and `end` in the archive at `path` as CSV.
+fn extract(path: &str, name: &str, start: f64, end: f64) {
+    let samples = ArchiveReader::open(path).and_then(|mut reader| reader.body(name, start, end));
+    let samples = match samples {
+        Ok(samples) => samples,
+        Err(error) => {
+            eprintln!("Cannot read archive {path}: {error}");
+            std::process::exit(1);
+        }
+    };
+    if samples.is_empty() {
+        eprintln!("No {name} in {path} between {start} and {end}");
+        std::process::exit(1);
+    }
+    println!("t,x,y,vx,vy");
+    for (time, body) in samples {
+        println!("{time},{},{},{},{}", body.position.x, body.position.y, body.velocity.x, body.velocity.y);
+    }
+}
+
+/// Plays the archive or checkpoint directory at `path` back in the terminal.
+fn replay(path: &str) {
+    let replay = match Replay::open(path) {
+        Ok(replay) => replay,
+        Err(error) => {
+            eprintln!("Cannot open recording {path}: {error}");
+            std::process::exit(1);
+        }
+    };
+    let result = TerminalInterface::new().and_then(|mut interface| {
+        let result = interface.replay(replay);
+        interface.restore()?;
+        result
+    });
+    if let Err(error) = result {
+        eprintln!("Terminal error: {error}");
+        std::process::exit(1);
+    }
+}
+
+/// The recorder asked for with `--record <file.csv|file.ndjson|file.traj|-|-ndjson> [--every <steps>] [--only <a,b>]`, if any,
+/// and whether it writes to stdout; removes these options from `args`. "-" writes CSV and "-ndjson" NDJSON to stdout.
+fn recorder_from(args: &mut Vec<String>) -> Option<(Recorder, bool)> {
+    let mut take = |flag: &str| {
+        let at = args.iter().position(|arg| arg == flag)?;
+        let value = args.get(at + 1).cloned();
+        args.drain(at..(at + 2).min(args.len()));
+        value
+    };
+    let (target, every, only) = (take("--record")?, take("--every"), take("--only"));
+    let recorder = match target.as_str() {
+        "-" => Recorder::stdout(Format::Csv),
+        "-ndjson" => Recorder::stdout(Format::Ndjson),
+        path => Recorder::create(path),
+    };
+    let recorder = match recorder {
+        Ok(recorder) => recorder,
+        Err(error) => {
+            eprintln!("Cannot create {target}: {error}");
+            std::process::exit(1);
+        }
+    };
+    let recorder = recorder.every(every.and_then(|every| every.parse().ok()).unwrap_or(1));
+    let recorder = match only {
+        Some(names) => recorder.only(&names.split(',').collect::<Vec<_>>()),
+        None => recorder,
+    };
+    Some((recorder, target.starts_with('-')))
+}
 
 fn main() {
+    // gravity [scenario.json [steps]] [--save end.json] or gravity --resume checkpoint [steps], each with the options of `recorder_from`;
+    // gravity --view [scenario.json] and gravity --replay <archive.traj|checkpoint directory> open the terminal viewer;
+    // gravity --extract <archive.traj> <body> [start end] prints one body's states from an archive
+    let mut args: Vec<String> = std::env::args().collect();
+    let recorder = recorder_from(&mut args);
+    let save = save_from(&mut args);
+    let quiet = recorder.as_ref().is_some_and(|(_, stdout)| *stdout); // keep stdout machine readable
+    let recorder = recorder.map(|(recorder, _)| recorder);
+    let steps = |at: usize| args.get(at).and_then(|steps| steps.parse().ok()).unwrap_or(1000);
+    match args.get(1).map(String::as_str) {
+        Some("--view") => {
+            view(args.get(2).map(String::as_str), recorder);
+            return;
+        }
+        Some("--replay") => {
+            let Some(path) = args.get(2) else {
+                eprintln!("Usage: gravity --replay <archive.traj|checkpoint directory>");
+                std::process::exit(2);
+            };
+            replay(path);
+            return;
+        }
+        Some("--extract") => {
+            let (Some(path), Some(name)) = (args.get(2), args.get(3)) else {
+                eprintln!("Usage: gravity --extract <archive.traj> <body> [start end]");
+                std::process::exit(2);
+            };
+            let time = |at: usize, default: f64| args.get(at).and_then(|time| time.parse().ok()).unwrap_or(default);
+            extract(path, name, time(4, f64::NEG_INFINITY), time(5, f64::INFINITY));
+            return;
+        }
+        Some("--resume") => {
+            let Some(path) = args.get(2) else {
+                eprintln!("Usage: gravity --resume <checkpoint> [steps]");
+                std::process::exit(2);
+            };
+            resume(path, steps(3), recorder, quiet);
+            return;
+        }
+        Some(path) => {
+            run_scenario(path, steps(2), recorder, save.as_deref(), quiet);
+            return;
+        }
+        None => {}
+    }
+
     let bodies = vec![
         CelestialObject::new(
             "Sun".to_string(),
@@ -39,7 +247,7 @@ fn main() {
     let time_step = 0.1;
     let iterations = 1000;
 
-    let mut simulation = Simulation::new(bodies, theta, time_step);
+    let mut simulation = Simulation::new(bodies, Box::new(BarnesHut::new(theta)), time_step, Box::new(VelocityVerlet));
     simulation.run(iterations, false);
 
     for body in &simulation.bodies {
@@ -0,0 +1,165 @@
+use nalgebra::{Point2, Vector2};
+
+use crate::engine::diagnostics::barycentre;
+use crate::space::objects::{CelestialObject, Position, Velocity};
+
+/// A reference frame to view the bodies in.
+///
+/// Bodies are always integrated in the inertial frame they are stored in; frames only change how
+/// positions and velocities are reported, for the TUI and the exporters.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Frame {
+    /// The frame the bodies are stored and integrated in.
+    #[default]
+    Inertial,
+    /// Origin at the center of mass, moving with it.
+    Barycentric,
+    /// Origin at the named body, moving with it but not rotating, e.g. heliocentric.
+    Body(String),
+    /// Origin at the barycentre of the named pair, rotating with it so that the first body stays on
+    /// the -x axis and the second on the +x axis.
+    Corotating(String, String),
+}
+
+/// Instantaneous placement of a frame: origin, its velocity, orientation and angular velocity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Placement {
+    origin: Position,
+    velocity: Velocity,
+    angle: f64,
+    spin: f64, // radians per unit time, counterclockwise
+}
+
+fn rotate(v: Vector2<f64>, angle: f64) -> Vector2<f64> {
+    let (sin, cos) = angle.sin_cos();
+    Vector2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+}
+
+/// ω × r for a rotation about the z axis.
+fn cross(spin: f64, r: Vector2<f64>) -> Vector2<f64> {
+    Vector2::new(-spin * r.y, spin * r.x)
+}
+
+impl Frame {
+    pub fn label(&self) -> String {
+        match self {
+            Frame::Inertial => "inertial".to_string(),
+            Frame::Barycentric => "barycentric".to_string(),
+            Frame::Body(name) => format!("{name}-centric"),
+            Frame::Corotating(a, b) => format!("co-rotating {a}-{b}"),
+        }
+    }
+
+    /// Where the frame is for the given bodies, None if it refers to a body that does not exist.
+    fn placement(&self, bodies: &[CelestialObject]) -> Option<Placement> {
+        let find = |name: &String| bodies.iter().find(|body| &body.name == name);
+        match self {
+            Frame::Inertial => Some(Placement { origin: Point2::origin(), velocity: Vector2::new(0.0, 0.0), angle: 0.0, spin: 0.0 }),
+            Frame::Barycentric => {
+                let (origin, velocity) = barycentre(bodies);
+                Some(Placement { origin, velocity, angle: 0.0, spin: 0.0 })
+            }
+            Frame::Body(name) => {
+                let body = find(name)?;
+                Some(Placement { origin: body.position, velocity: body.velocity, angle: 0.0, spin: 0.0 })
+            }
+            Frame::Corotating(a, b) => {
+                let pair = [find(a)?.clone(), find(b)?.clone()];
+                let (origin, velocity) = barycentre(&pair);
+                let r = pair[1].position - pair[0].position;
+                let v = pair[1].velocity - pair[0].velocity;
+                let spin = if r.norm_squared() > 0.0 { r.perp(&v) / r.norm_squared() } else { 0.0 };
+                Some(Placement { origin, velocity, angle: r.y.atan2(r.x), spin })
+            }
+        }
+    }
+
+    /// Position and velocity of a stored (inertial) state as seen in this frame.
+    pub fn to_frame(&self, bodies: &[CelestialObject], position: Position, velocity: Velocity) -> Option<(Position, Velocity)> {
+        let frame = self.placement(bodies)?;
+        let r = rotate(position - frame.origin, -frame.angle);
+        let v = rotate(velocity - frame.velocity, -frame.angle) - cross(frame.spin, r);
+        Some((Point2::from(r), v))
+    }
+
+    /// Inertial position and velocity of a state given in this frame, e.g. to place a body at a Lagrange point.
+    pub fn to_inertial(&self, bodies: &[CelestialObject], position: Position, velocity: Velocity) -> Option<(Position, Velocity)> {
+        let frame = self.placement(bodies)?;
+        let r = position.coords;
+        let v = rotate(velocity + cross(frame.spin, r), frame.angle);
+        Some((frame.origin + rotate(r, frame.angle), frame.velocity + v))
+    }
+
+    /// Copies of `bodies` seen in this frame. Accelerations are only rotated, without the fictitious
+    /// terms of a rotating frame. None if the frame refers to a body that does not exist.
+    pub fn transform(&self, bodies: &[CelestialObject]) -> Option<Vec<CelestialObject>> {
+        let frame = self.placement(bodies)?;
+        let r = |position: Position| rotate(position - frame.origin, -frame.angle);
+        Some(bodies.iter()
+            .map(|body| {
+                let mut seen = body.clone();
+                let position = r(body.position);
+                seen.position = Point2::from(position);
+                seen.prevposition = Point2::from(r(body.prevposition));
+                seen.velocity = rotate(body.velocity - frame.velocity, -frame.angle) - cross(frame.spin, position);
+                seen.acceleration = rotate(body.acceleration, -frame.angle);
+                seen
+            })
+            .collect())
+    }
+}
+
+/// Moves the origin to the barycentre of `bodies` and removes their total momentum.
+pub fn centre(bodies: &mut [CelestialObject]) {
+    let (origin, velocity) = barycentre(bodies);
+    for body in bodies.iter_mut() {
+        body.position -= origin.coords;
+        body.prevposition -= origin.coords;
+        body.velocity -= velocity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bodies() -> Vec<CelestialObject> {
+        [("A", 3.0, (1.0, -2.0), (0.3, 0.1)), ("B", 1.0, (-0.5, 1.5), (-0.2, 0.4)), ("C", 0.5, (4.0, 2.0), (0.0, -0.7))]
+            .map(|(name, mass, (x, y), (vx, vy))| {
+                let position = Point2::new(x, y);
+                CelestialObject::new(name.to_string(), mass, position, Vector2::new(vx, vy), Vector2::zeros(), position)
+            })
+            .to_vec()
+    }
+
+    #[test]
+    fn frames_invert() {
+        let bodies = bodies();
+        let frames = [
+            Frame::Inertial,
+            Frame::Barycentric,
+            Frame::Body("B".to_string()),
+            Frame::Corotating("A".to_string(), "B".to_string()),
+        ];
+        for frame in frames {
+            for body in &bodies {
+                let (position, velocity) = frame.to_frame(&bodies, body.position, body.velocity).unwrap();
+                let (back, back_velocity) = frame.to_inertial(&bodies, position, velocity).unwrap();
+                assert!((back - body.position).norm() < 1e-12, "{}: {} moved to {back}", frame.label(), body.name);
+                assert!((back_velocity - body.velocity).norm() < 1e-12, "{}: {} velocity changed", frame.label(), body.name);
+            }
+        }
+    }
+
+    #[test]
+    fn corotating_pair_sits_still_on_the_x_axis() {
+        let bodies = bodies();
+        let seen = Frame::Corotating("A".to_string(), "B".to_string()).transform(&bodies).unwrap();
+        assert!(seen[0].position.y.abs() < 1e-12 && seen[0].position.x < 0.0);
+        assert!(seen[1].position.y.abs() < 1e-12 && seen[1].position.x > 0.0);
+        assert!((seen[0].position.x * 3.0 + seen[1].position.x).abs() < 1e-12, "origin is not the pair's barycentre");
+        // only the radial motion of the pair is left
+        assert!(seen[0].velocity.y.abs() < 1e-12 && seen[1].velocity.y.abs() < 1e-12);
+        assert!(Frame::Body("Z".to_string()).transform(&bodies).is_none());
+    }
+}
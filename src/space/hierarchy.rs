@@ -0,0 +1,196 @@
+use crate::space::lagrange::hill_radius;
+use crate::space::objects::CelestialObject;
+use crate::space::orbits::OrbitalElements;
+
+/// Above this mass ratio a body and its parent are classified as a binary rather than a
+/// satellite and its primary (Pluto and Charon are ~0.12, the Earth and the Moon ~0.012).
+pub const BINARY_MASS_RATIO: f64 = 0.1;
+
+/// Place of a body in the orbital hierarchy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// Top of a hierarchy: the heaviest body, or any parentless body with satellites.
+    Star,
+    /// Orbits a star.
+    Planet,
+    /// Orbits a planet, or a moon.
+    Moon,
+    /// One of two bound bodies of comparable mass, see `BINARY_MASS_RATIO`.
+    BinaryMember,
+    /// Bound to nothing heavier and orbited by nothing, e.g. a passing rogue.
+    Unbound,
+}
+
+impl Role {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Role::Star => "star",
+            Role::Planet => "planet",
+            Role::Moon => "moon",
+            Role::BinaryMember => "binary",
+            Role::Unbound => "unbound",
+        }
+    }
+}
+
+/// One body in a `Hierarchy`; indices refer to the bodies it was detected from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Node {
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+    pub role: Role,
+    pub level: usize, // 0 for stars, 1 for planets, 2 and more for moons; binary companions share a level
+}
+
+/// Which body orbits which, as a forest with one node per body.
+///
+/// A body's parent is the body it is bound to (negative two-body energy) that sits deepest in the
+/// tree while still holding it inside its Hill sphere. Bodies are placed heaviest first, and only
+/// heavier bodies can be parents, so test particles never are. For the Moon both the Sun and the
+/// Earth qualify, and the Earth wins as the deeper one.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Hierarchy {
+    pub nodes: Vec<Node>,
+}
+
+impl Hierarchy {
+    /// Infers the hierarchy of `bodies`, with `g` the gravitational constant in their units.
+    pub fn detect(bodies: &[CelestialObject], g: f64) -> Self {
+        let mut order: Vec<usize> = (0..bodies.len()).collect();
+        order.sort_by(|&a, &b| bodies[b].mass.total_cmp(&bodies[a].mass));
+
+        let mut nodes: Vec<Node> = bodies.iter()
+            .map(|_| Node { parent: None, children: Vec::new(), role: Role::Unbound, level: 0 })
+            .collect();
+        let mut depth = vec![0; bodies.len()];
+
+        for (rank, &i) in order.iter().enumerate() {
+            let body = &bodies[i];
+            let parent = order[..rank].iter()
+                .copied()
+                .filter(|&j| bodies[j].mass > body.mass)
+                .filter(|&j| {
+                    let r = (body.position - bodies[j].position).norm();
+                    let v = (body.velocity - bodies[j].velocity).norm();
+                    r > 0.0 && v * v / 2.0 < g * (body.mass + bodies[j].mass) / r
+                })
+                .filter(|&j| match nodes[j].parent {
+                    Some(grandparent) => (body.position - bodies[j].position).norm() < hill_radius(&bodies[grandparent], &bodies[j], g),
+                    None => true,
+                })
+                .max_by(|&a, &b| {
+                    let distance = |j: usize| (body.position - bodies[j].position).norm();
+                    depth[a].cmp(&depth[b]).then(distance(b).total_cmp(&distance(a)))
+                });
+
+            if let Some(parent) = parent {
+                nodes[i].parent = Some(parent);
+                nodes[parent].children.push(i);
+                depth[i] = depth[parent] + 1;
+                let binary = body.mass >= BINARY_MASS_RATIO * bodies[parent].mass;
+                nodes[i].level = if binary { nodes[parent].level } else { nodes[parent].level + 1 };
+                if binary {
+                    nodes[i].role = Role::BinaryMember;
+                    nodes[parent].role = Role::BinaryMember;
+                }
+            }
+        }
+
+        let heaviest = order.first().copied();
+        for (i, node) in nodes.iter_mut().enumerate() {
+            if node.role == Role::BinaryMember {
+                continue;
+            }
+            node.role = match (node.parent, node.level) {
+                (None, _) if Some(i) == heaviest || !node.children.is_empty() => Role::Star,
+                (None, _) => Role::Unbound,
+                (Some(_), 1) => Role::Planet,
+                (Some(_), _) => Role::Moon,
+            };
+        }
+        Hierarchy { nodes }
+    }
+
+    pub fn roots(&self) -> Vec<usize> {
+        (0..self.nodes.len()).filter(|&i| self.nodes[i].parent.is_none()).collect()
+    }
+
+    pub fn parent(&self, index: usize) -> Option<usize> {
+        self.nodes.get(index)?.parent
+    }
+
+    pub fn role(&self, index: usize) -> Option<Role> {
+        self.nodes.get(index).map(|node| node.role)
+    }
+
+    /// Every body once, depth first with parents before their children, as (depth, index) pairs;
+    /// children come in the order they were found, heaviest first.
+    pub fn walk(&self) -> Vec<(usize, usize)> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack: Vec<(usize, usize)> = self.roots().into_iter().rev().map(|i| (0, i)).collect();
+        while let Some((depth, i)) = stack.pop() {
+            order.push((depth, i));
+            stack.extend(self.nodes[i].children.iter().rev().map(|&child| (depth + 1, child)));
+        }
+        order
+    }
+
+    /// Elements of every body's orbit around its parent, None for parentless bodies.
+    pub fn elements(&self, bodies: &[CelestialObject], g: f64) -> Vec<Option<OrbitalElements>> {
+        self.nodes.iter()
+            .zip(bodies)
+            .map(|(node, body)| node.parent.map(|parent| body.elements_around(&bodies[parent], g)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::objects::G;
+    use crate::space::system::StarSystem;
+
+    use nalgebra::{Point2, Vector2};
+
+    #[test]
+    fn sun_planets_and_a_moon() {
+        let mut bodies = StarSystem::solar().bodies;
+        let earth = bodies.iter().position(|body| body.name == "Earth").unwrap();
+        let orbit = OrbitalElements::new(3.844e8, 0.055, 0.0, 1.0);
+        bodies.push(CelestialObject::from_elements("Moon".into(), 7.342e22, &bodies[earth], &orbit, G));
+        let moon = bodies.len() - 1;
+        let hierarchy = Hierarchy::detect(&bodies, G);
+
+        assert_eq!(hierarchy.roots(), vec![0]);
+        assert_eq!(hierarchy.role(0), Some(Role::Star));
+        for (i, planet) in bodies.iter().enumerate().take(moon).skip(1) {
+            assert_eq!((hierarchy.parent(i), hierarchy.role(i)), (Some(0), Some(Role::Planet)), "{}", planet.name);
+        }
+        assert_eq!((hierarchy.parent(moon), hierarchy.role(moon)), (Some(earth), Some(Role::Moon)));
+        assert_eq!(hierarchy.nodes[moon].level, 2);
+        assert_eq!(hierarchy.walk().iter().filter(|&&(depth, _)| depth == 2).count(), 1);
+    }
+
+    #[test]
+    fn binary_with_a_planet_and_a_rogue() {
+        // G = 1: stars of mass 1 and 0.5 a unit apart, a planet close around the heavier one and a fast passer-by
+        let body = |name: &str, mass: f64, position: [f64; 2], velocity: [f64; 2]| {
+            let position = Point2::new(position[0], position[1]);
+            CelestialObject::new(name.into(), mass, position, Vector2::new(velocity[0], velocity[1]), Vector2::zeros(), position)
+        };
+        let speed = 1.5f64.sqrt(); // relative speed of the circular binary
+        let bodies = vec![
+            body("A", 1.0, [-1.0 / 3.0, 0.0], [0.0, -speed / 3.0]),
+            body("B", 0.5, [2.0 / 3.0, 0.0], [0.0, 2.0 * speed / 3.0]),
+            body("Planet", 1e-3, [-1.0 / 3.0, 0.1], [-10f64.sqrt(), -speed / 3.0]),
+            body("Rogue", 1e-3, [0.0, 30.0], [5.0, 0.0]),
+        ];
+        let hierarchy = Hierarchy::detect(&bodies, 1.0);
+
+        assert_eq!(hierarchy.role(0), Some(Role::BinaryMember));
+        assert_eq!((hierarchy.parent(1), hierarchy.role(1)), (Some(0), Some(Role::BinaryMember)));
+        assert_eq!(hierarchy.nodes[0].level, hierarchy.nodes[1].level);
+        assert_eq!((hierarchy.parent(2), hierarchy.role(2)), (Some(0), Some(Role::Planet)));
+        assert_eq!((hierarchy.parent(3), hierarchy.role(3)), (None, Some(Role::Unbound)));
+    }
+}
@@ -0,0 +1,196 @@
+use std::f64::consts::PI;
+
+use nalgebra::{Point2, Vector2};
+
+use crate::space::objects::CelestialObject;
+
+/// Stumpff function C(z) = (1 - cos √z) / z, continued to z ≤ 0.
+pub fn stumpff_c(z: f64) -> f64 {
+    if z > 1e-8 {
+        (1.0 - z.sqrt().cos()) / z
+    } else if z < -1e-8 {
+        ((-z).sqrt().cosh() - 1.0) / -z
+    } else {
+        0.5 - z / 24.0
+    }
+}
+
+/// Stumpff function S(z) = (√z - sin √z) / √z³, continued to z ≤ 0.
+pub fn stumpff_s(z: f64) -> f64 {
+    if z > 1e-8 {
+        let root = z.sqrt();
+        (root - root.sin()) / root.powi(3)
+    } else if z < -1e-8 {
+        let root = (-z).sqrt();
+        (root.sinh() - root) / root.powi(3)
+    } else {
+        1.0 / 6.0 - z / 120.0
+    }
+}
+
+/// Exact two-body propagation of a relative state by `dt`, with the universal variable formulation.
+///
+/// `position` and `velocity` are relative to the other body and `mu` = G(M + m). Works for
+/// elliptic, parabolic and hyperbolic orbits alike; elliptic ones first drop whole periods from `dt`,
+/// so propagating over thousands of orbits costs the same as over one.
+pub fn propagate(position: Vector2<f64>, velocity: Vector2<f64>, mu: f64, dt: f64) -> (Vector2<f64>, Vector2<f64>) {
+    let r0 = position.norm();
+    if r0 == 0.0 || mu <= 0.0 {
+        return (position + velocity * dt, velocity);
+    }
+    let sqrt_mu = mu.sqrt();
+    let radial_velocity = position.dot(&velocity) / r0;
+    let alpha = 2.0 / r0 - velocity.norm_squared() / mu; // 1/a
+
+    let dt = if alpha > 0.0 {
+        let period = 2.0 * PI / (alpha.powi(3).sqrt() * sqrt_mu);
+        dt % period
+    } else {
+        dt
+    };
+
+    // Newton's method on the universal Kepler equation F(χ) = 0
+    let mut chi = if alpha > 0.0 { sqrt_mu * alpha * dt } else { sqrt_mu * dt / r0 };
+    for _ in 0..100 {
+        let z = alpha * chi * chi;
+        let (c, s) = (stumpff_c(z), stumpff_s(z));
+        let f = r0 * radial_velocity / sqrt_mu * chi * chi * c
+            + (1.0 - alpha * r0) * chi.powi(3) * s
+            + r0 * chi
+            - sqrt_mu * dt;
+        let df = r0 * radial_velocity / sqrt_mu * chi * (1.0 - z * s)
+            + (1.0 - alpha * r0) * chi * chi * c
+            + r0;
+        let step = f / df;
+        chi -= step;
+        if step.abs() <= 1e-13 * chi.abs().max(1.0) {
+            break;
+        }
+    }
+
+    // Lagrange coefficients
+    let z = alpha * chi * chi;
+    let (c, s) = (stumpff_c(z), stumpff_s(z));
+    let f = 1.0 - chi * chi / r0 * c;
+    let g = dt - chi.powi(3) * s / sqrt_mu;
+    let new_position = position * f + velocity * g;
+    let r = new_position.norm();
+    let f_dot = sqrt_mu / (r * r0) * (z * s - 1.0) * chi;
+    let g_dot = 1.0 - chi * chi / r * c;
+    (new_position, position * f_dot + velocity * g_dot)
+}
+
+/// Moves two bodies along their exact mutual orbit by `dt`, ignoring everything else.
+/// The barycentre drifts in a straight line; accelerations are set to the two-body ones.
+/// `g` is the gravitational constant in the units of the bodies.
+pub fn propagate_pair(a: &mut CelestialObject, b: &mut CelestialObject, dt: f64, g: f64) {
+    let mass = a.mass + b.mass;
+    if mass <= 0.0 {
+        return;
+    }
+    let center = (a.position.coords * a.mass + b.position.coords * b.mass) / mass;
+    let drift = (a.momentum() + b.momentum()) / mass;
+    let (r, v) = propagate(b.position - a.position, b.velocity - a.velocity, g * mass, dt);
+
+    let center = center + drift * dt;
+    a.prevposition = a.position;
+    b.prevposition = b.position;
+    a.position = Point2::from(center - r * b.mass / mass);
+    b.position = Point2::from(center + r * a.mass / mass);
+    a.velocity = drift - v * b.mass / mass;
+    b.velocity = drift + v * a.mass / mass;
+
+    let acceleration = -r * g / r.norm().powi(3);
+    a.acceleration = -acceleration * b.mass;
+    b.acceleration = acceleration * a.mass;
+}
+
+/// Largest position error of a simulated two-body run against the exact solution, relative to the
+/// separation at the time.
+///
+/// `initial` holds the two bodies at the start and every sample the time elapsed since and the same
+/// bodies after that much numerical integration, e.g. by `physics::simulate` or `Simulation::run`,
+/// all in the units of `g`. None if `initial` or a sample holds fewer than two bodies.
+pub fn position_error<'a>(
+    initial: &[CelestialObject],
+    samples: impl IntoIterator<Item = (f64, &'a [CelestialObject])>,
+    g: f64,
+) -> Option<f64> {
+    let [a, b, ..] = initial else { return None };
+    let mut largest: f64 = 0.0;
+    for (elapsed, evolved) in samples {
+        let [evolved_a, evolved_b, ..] = evolved else { return None };
+        let (mut a, mut b) = (a.clone(), b.clone());
+        propagate_pair(&mut a, &mut b, elapsed, g);
+        let separation = (b.position - a.position).norm();
+        let error = (evolved_a.position - a.position).norm().max((evolved_b.position - b.position).norm());
+        largest = largest.max(error / separation);
+    }
+    Some(largest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::integrator;
+    use crate::engine::physics;
+    use crate::engine::solver::DirectSum;
+    use crate::gen::sim::Simulation;
+    use crate::space::objects::{Gravity, G};
+    use crate::space::orbits::OrbitalElements;
+
+    const ORBITS: usize = 20;
+    const STEPS_PER_ORBIT: usize = 1000;
+
+    /// A Sun and an Earth mass planet on a 1 AU orbit of eccentricity 0.3, and its period.
+    fn pair() -> (Vec<CelestialObject>, f64) {
+        let sun = CelestialObject::new("Sun".into(), 1.989e30, Point2::origin(), Vector2::zeros(), Vector2::zeros(), Point2::origin());
+        let elements = OrbitalElements::new(1.496e11, 0.3, 0.0, 0.0);
+        let planet = CelestialObject::from_elements("Planet".into(), 5.972e24, &sun, &elements, G);
+        let period = elements.period(G * (sun.mass + planet.mass));
+        (vec![sun, planet], period)
+    }
+
+    /// Each integrator with the largest relative error it may make over `ORBITS` orbits.
+    const TOLERANCES: [(&str, f64); 5] = [("euler", 5e-2), ("verlet", 2e-2), ("leapfrog", 2e-2), ("rk4", 1e-6), ("yoshida4", 2e-6)];
+
+    /// Runs `run` with every integrator, which returns the bodies after each orbit, and checks them
+    /// against the exact orbit.
+    fn check(run: impl Fn(&str, &[CelestialObject], f64) -> Vec<Vec<CelestialObject>>) {
+        let (initial, period) = pair();
+        let dt = period / STEPS_PER_ORBIT as f64;
+        for (name, tolerance) in TOLERANCES {
+            let samples = run(name, &initial, dt);
+            let elapsed = (1..=ORBITS).map(|orbit| orbit as f64 * period);
+            let error = position_error(&initial, elapsed.zip(samples.iter().map(Vec::as_slice)), G).unwrap();
+            assert!(error < tolerance, "{name}: error {error} after {ORBITS} orbits");
+        }
+    }
+
+    #[test]
+    fn simulate_follows_kepler() {
+        check(|name, initial, dt| {
+            let integrator = integrator::by_name(name).unwrap();
+            let mut bodies = initial.to_vec();
+            (0..ORBITS)
+                .map(|_| {
+                    physics::simulate(&mut bodies, &DirectSum, integrator.as_ref(), Gravity::default(), dt, STEPS_PER_ORBIT);
+                    bodies.clone()
+                })
+                .collect()
+        });
+    }
+
+    #[test]
+    fn simulation_run_follows_kepler() {
+        check(|name, initial, dt| {
+            let mut simulation = Simulation::new(initial.to_vec(), Box::new(DirectSum), dt, integrator::by_name(name).unwrap());
+            (0..ORBITS)
+                .map(|_| {
+                    simulation.run(STEPS_PER_ORBIT, false);
+                    simulation.bodies.clone()
+                })
+                .collect()
+        });
+    }
+}
@@ -0,0 +1,139 @@
+use nalgebra::{Point2, Vector2};
+
+use crate::space::frame::Frame;
+use crate::space::objects::{CelestialObject, Position, Velocity};
+
+/// A Lagrange point of a pair at one instant, in the inertial frame of the bodies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LagrangePoint {
+    pub position: Position,
+    pub velocity: Velocity, // co-rotating with the pair
+}
+
+/// x coordinates of L1, L2 and L3 for a unit separation, with the pair's barycentre at the origin,
+/// the primary at -μ and the secondary at 1 - μ, where μ = m2 / (m1 + m2).
+///
+/// They are the roots of the collinear equilibrium condition
+/// x - (1 - μ)(x + μ) / |x + μ|³ - μ(x - 1 + μ) / |x - 1 + μ|³ = 0,
+/// one in each interval between and beyond the bodies, found by bisection.
+pub fn collinear_points(mu: f64) -> [f64; 3] {
+    let f = |x: f64| {
+        let (d1, d2) = (x + mu, x - 1.0 + mu);
+        x - (1.0 - mu) * d1 / d1.abs().powi(3) - mu * d2 / d2.abs().powi(3)
+    };
+    let bisect = |mut low: f64, mut high: f64| {
+        // f goes from -∞ to +∞ across each interval
+        for _ in 0..200 {
+            let middle = 0.5 * (low + high);
+            if f(middle) < 0.0 {
+                low = middle;
+            } else {
+                high = middle;
+            }
+            if high - low < 1e-15 {
+                break;
+            }
+        }
+        0.5 * (low + high)
+    };
+    let gap = 1e-12;
+    [
+        bisect(-mu + gap, 1.0 - mu - gap),
+        bisect(1.0 - mu + gap, 2.0),
+        bisect(-2.0, -mu - gap),
+    ]
+}
+
+/// The five Lagrange points L1..L5 of `secondary` around `primary`, for their current separation.
+///
+/// The points are computed in the frame co-rotating with the pair and carried back to the inertial
+/// frame, so each comes with the velocity it has rotating along with the pair. L4 leads the
+/// secondary by 60° and L5 trails it. Other bodies are ignored.
+pub fn lagrange_points(primary: &CelestialObject, secondary: &CelestialObject) -> [LagrangePoint; 5] {
+    let mass = primary.mass + secondary.mass;
+    let mu = if mass > 0.0 { secondary.mass / mass } else { 0.0 };
+    let separation = (secondary.position - primary.position).norm();
+
+    let [l1, l2, l3] = collinear_points(mu);
+    let height = 3f64.sqrt() / 2.0;
+    let points = [(l1, 0.0), (l2, 0.0), (l3, 0.0), (0.5 - mu, height), (0.5 - mu, -height)];
+
+    let pair = [primary.clone(), secondary.clone()];
+    let frame = Frame::Corotating(primary.name.clone(), secondary.name.clone());
+    points.map(|(x, y)| {
+        let (position, velocity) = frame
+            .to_inertial(&pair, Point2::new(x, y) * separation, Vector2::new(0.0, 0.0))
+            .unwrap();
+        LagrangePoint { position, velocity }
+    })
+}
+
+/// Hill sphere radius r_H = a(1 - e)(m / 3M)^(1/3): how far out the secondary can hold satellites
+/// against the primary's tide. `g` is the gravitational constant in the bodies' units.
+pub fn hill_radius(primary: &CelestialObject, secondary: &CelestialObject, g: f64) -> f64 {
+    let elements = secondary.elements_around(primary, g);
+    elements.periapsis() * (secondary.mass / (3.0 * primary.mass)).cbrt()
+}
+
+/// Laplace sphere of influence r_SOI = a(m / M)^(2/5), inside which the secondary is the better
+/// center for patched-conic orbits.
+pub fn sphere_of_influence(primary: &CelestialObject, secondary: &CelestialObject, g: f64) -> f64 {
+    let elements = secondary.elements_around(primary, g);
+    elements.semi_major_axis.abs() * (secondary.mass / primary.mass).powf(0.4)
+}
+
+/// Test particles scattered around L4 and L5 of the pair, `per_point` at each, to study Trojans.
+///
+/// They fill a disc of radius `spread` times the separation around each point on a sunflower
+/// pattern, and co-rotate with the pair. Named "<secondary> L4 #i" and "<secondary> L5 #i".
+pub fn trojans(primary: &CelestialObject, secondary: &CelestialObject, per_point: usize, spread: f64) -> Vec<CelestialObject> {
+    let points = lagrange_points(primary, secondary);
+    let separation = (secondary.position - primary.position).norm();
+    let golden_angle = std::f64::consts::PI * (3.0 - 5f64.sqrt());
+
+    let pair = [primary.clone(), secondary.clone()];
+    let frame = Frame::Corotating(primary.name.clone(), secondary.name.clone());
+    let mut bodies = Vec::with_capacity(2 * per_point);
+    for (label, point) in [("L4", points[3]), ("L5", points[4])] {
+        let (center, _) = frame.to_frame(&pair, point.position, point.velocity).unwrap();
+        for i in 0..per_point {
+            let radius = spread * separation * ((i as f64 + 0.5) / per_point as f64).sqrt();
+            let angle = i as f64 * golden_angle;
+            let offset = Vector2::new(angle.cos(), angle.sin()) * radius;
+            let (position, velocity) = frame.to_inertial(&pair, center + offset, Vector2::new(0.0, 0.0)).unwrap();
+            bodies.push(CelestialObject::test_particle(format!("{} {} #{}", secondary.name, label, i), position, velocity));
+        }
+    }
+    bodies
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::space::system::StarSystem;
+
+    #[test]
+    fn sun_earth_points_and_spheres() {
+        let system = StarSystem::solar();
+        let (sun, earth) = (&system.bodies[0], &system.bodies[3]);
+        let separation = (earth.position - sun.position).norm();
+        let points = system.lagrange_points("Sun", "Earth").unwrap();
+
+        // L1 and L2 lie about a Hill radius, ~1.5 million km, either side of the Earth
+        let hill = system.hill_radius("Sun", "Earth").unwrap();
+        assert!((hill / 1.5e9 - 1.0).abs() < 0.05, "Hill radius {hill}");
+        for point in &points[..2] {
+            let distance = (point.position - earth.position).norm();
+            assert!((distance / hill - 1.0).abs() < 0.05, "{distance} from the Earth");
+        }
+        // L3 just beyond the Earth's orbit on the far side, L4 and L5 on equilateral triangles
+        assert!(((points[2].position - sun.position).norm() / separation - 1.0).abs() < 1e-3);
+        for point in &points[3..] {
+            assert!(((point.position - sun.position).norm() / separation - 1.0).abs() < 1e-6);
+            assert!(((point.position - earth.position).norm() / separation - 1.0).abs() < 1e-6);
+        }
+
+        let influence = system.sphere_of_influence("Sun", "Earth").unwrap();
+        assert!((influence / 9.25e8 - 1.0).abs() < 0.02, "sphere of influence {influence}");
+        assert_eq!(system.lagrange_points("Sun", "Vulcan"), None);
+    }
+}
@@ -1,2 +1,9 @@
 pub mod objects;
-pub mod system;
\ No newline at end of file
+pub mod system;
+pub mod orbits;
+pub mod kepler;
+pub mod units;
+pub mod frame;
+pub mod lagrange;
+pub mod hierarchy;
+pub mod scenario;
\ No newline at end of file
@@ -1,5 +1,3 @@
-#[allow(dead_code)]
-
 use nalgebra::{Point2, Vector2};
 
 pub type Velocity = Vector2<f64>;
@@ -9,15 +7,44 @@ pub type Acceleration = Vector2<f64>;
 pub type Distance = Vector2<f64>;
 pub type Force = Vector2<f64>;
 
-pub const G: f64 = 6.67428e-11; // gravitational constant, in m^3 kg^-1 s^-2
+pub const G: f64 = 6.67430e-11; // gravitational constant, in m^3 kg^-1 s^-2 (CODATA 2018)
 pub const AU: f64 = 1.4960e+11; // astronomical units, ~distance between sun and earth
+pub const C: f64 = 299_792_458.0; // speed of light, in m s^-1
+
+/// Constants of the pairwise gravity kernel, in the units the bodies are given in.
+///
+/// `g` and `c` come from the system's `Units`; the default is SI. `softening` is the system wide
+/// Plummer length ε, which bodies may override with their own.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Gravity {
+    pub g: f64,
+    pub c: f64,
+    pub softening: f64,
+}
+
+impl Default for Gravity {
+    fn default() -> Self {
+        Gravity { g: G, c: C, softening: 0.0 }
+    }
+}
 
-pub trait Sim {
-    fn get_name(&self) -> String;
-    fn get_position(&self) -> Position; // Point2<T>
-    fn get_velocity(&self) -> Velocity; // Vector2<T>
-    fn get_mass(&self) -> f64;
-    // fn simulate(&mut self)
+impl Gravity {
+    pub fn with_softening(mut self, softening: f64) -> Self {
+        self.softening = softening;
+        self
+    }
+}
+
+/// Physical nature of a body, as opposed to its place in the orbital hierarchy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BodyKind {
+    #[default]
+    Regular,
+    /// Point mass whose `radius` is its event horizon; anything whose center crosses it is absorbed.
+    BlackHole,
+    /// Massless probe such as a ring or belt particle: feels the gravity of the massive bodies but
+    /// exerts none, and is never a mass source in any solver. Its `mass` is 0.
+    TestParticle,
 }
 
 #[derive(Clone, Debug, Default)]
@@ -28,6 +55,10 @@ pub struct CelestialObject {
     pub velocity: Velocity, // x, y components
     pub acceleration: Acceleration,
     pub prevposition: Position,
+    pub radius: f64, // physical radius used for collisions, 0 for a point mass
+    pub kind: BodyKind,
+    pub softening: Option<f64>, // own Plummer softening length, overrides the system's
+    pub colour: Option<[u8; 3]>, // RGB to draw the body in, None for the colour of its kind
 }
 
 impl CelestialObject {
@@ -45,20 +76,109 @@ impl CelestialObject {
             position,
             velocity,
             acceleration,
-            prevposition: position,
+            prevposition,
+            radius: 0.0,
+            kind: BodyKind::Regular,
+            softening: None,
+            colour: None,
         }
     }
+
+    /// A black hole at rest in its own frame, with its radius set to the event horizon for the G and c
+    /// of `gravity`, which must be in the units the body is given in.
+    pub fn black_hole(name: String, mass: Mass, position: Position, velocity: Velocity, gravity: Gravity) -> Self {
+        let mut hole = CelestialObject::new(name, mass, position, velocity, Vector2::new(0.0, 0.0), position);
+        hole.kind = BodyKind::BlackHole;
+        hole.radius = hole.horizon_radius(gravity);
+        hole
+    }
+
+    pub fn is_black_hole(&self) -> bool {
+        self.kind == BodyKind::BlackHole
+    }
+
+    /// A massless test particle, see `BodyKind::TestParticle`.
+    pub fn test_particle(name: String, position: Position, velocity: Velocity) -> Self {
+        let mut particle = CelestialObject::new(name, 0.0, position, velocity, Vector2::new(0.0, 0.0), position);
+        particle.kind = BodyKind::TestParticle;
+        particle
+    }
+
+    pub fn is_test_particle(&self) -> bool {
+        self.kind == BodyKind::TestParticle
+    }
+
+    /// r_s = 2GM/c², the event horizon of a non-rotating mass, with G and c in the units of `gravity`.
+    pub fn horizon_radius(&self, gravity: Gravity) -> f64 {
+        2.0 * gravity.g * self.mass / (gravity.c * gravity.c)
+    }
+
+    pub fn with_radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Sets the radius of a uniform sphere of the given density (kg/m^3) and the body's mass.
+    pub fn with_density(mut self, density: f64) -> Self {
+        self.radius = (3.0 * self.mass / (4.0 * std::f64::consts::PI * density)).cbrt();
+        self
+    }
+
+    pub fn momentum(&self) -> Vector2<f64> {
+        self.velocity * self.mass
+    }
+
+    pub fn overlaps(&self, other: &CelestialObject) -> bool {
+        self.get_distance(&other.position).norm() < self.radius + other.radius
+    }
     pub fn get_distance(&self, other: &Position) -> Distance {
         other - self.position
     }
 
     pub fn get_force(&self, other: &CelestialObject) -> Force {
+        self.get_softened_force(other, Gravity::default())
+    }
+
+    pub fn with_softening(mut self, softening: f64) -> Self {
+        self.softening = Some(softening);
+        self
+    }
+
+    pub fn with_colour(mut self, colour: [u8; 3]) -> Self {
+        self.colour = Some(colour);
+        self
+    }
+
+    /// Squared softening length of the pair, the mean of both bodies' ε², each falling back to `softening`.
+    pub fn pair_softening_squared(&self, other: &CelestialObject, softening: f64) -> f64 {
+        let own = self.softening.unwrap_or(softening);
+        let theirs = other.softening.unwrap_or(softening);
+        0.5 * (own * own + theirs * theirs)
+    }
+
+    /// Plummer-softened attraction towards `other`: G m1 m2 r / (r² + ε²)^(3/2).
+    /// Reduces to Newton's law for ε = 0, and vanishes smoothly instead of diverging as r → 0.
+    pub fn get_softened_force(&self, other: &CelestialObject, gravity: Gravity) -> Force {
         let dist = self.get_distance(&other.position);
-        if dist.norm() == 0.0 {
+        let distance_squared = dist.norm_squared();
+        if distance_squared == 0.0 {
             return Vector2::new(0.0, 0.0);
         }
-        let f = (G * self.mass * other.mass) / dist.norm_squared();
-        f * dist.normalize()
+        let softened = distance_squared + self.pair_softening_squared(other, gravity.softening);
+        dist * (gravity.g * self.mass * other.mass) / (softened * softened.sqrt())
+    }
+
+    /// Potential energy of the pair, -G m1 m2 / (r² + ε²)^(1/2), zero for the body itself.
+    pub fn get_softened_potential(&self, other: &CelestialObject, gravity: Gravity) -> f64 {
+        let distance_squared = self.get_distance(&other.position).norm_squared();
+        if distance_squared == 0.0 {
+            return 0.0;
+        }
+        -gravity.g * self.mass * other.mass / (distance_squared + self.pair_softening_squared(other, gravity.softening)).sqrt()
+    }
+
+    pub fn kinetic_energy(&self) -> f64 {
+        0.5 * self.mass * self.velocity.norm_squared()
     }
 }
 
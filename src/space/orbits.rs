@@ -0,0 +1,310 @@
+use std::f64::consts::PI;
+
+use nalgebra::Vector2;
+
+use crate::space::objects::{CelestialObject, Mass, Position, Velocity};
+
+/// Classical Keplerian elements of an orbit around a parent body.
+///
+/// The simulation is planar, so an orbit is either prograde (`inclination` 0) or retrograde
+/// (`inclination` π), and the ascending node is the +x axis by convention (`ascending_node` 0).
+/// Angles are in radians. Hyperbolic orbits have e > 1 and a negative semi-major axis.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    pub true_anomaly: f64,
+}
+
+impl OrbitalElements {
+    /// Elements of an ellipse at the given true anomaly, prograde, periapsis along `argument_of_periapsis`.
+    pub fn new(semi_major_axis: f64, eccentricity: f64, argument_of_periapsis: f64, true_anomaly: f64) -> Self {
+        OrbitalElements {
+            semi_major_axis,
+            eccentricity,
+            inclination: 0.0,
+            ascending_node: 0.0,
+            argument_of_periapsis,
+            true_anomaly,
+        }
+    }
+
+    pub fn retrograde(mut self) -> Self {
+        self.inclination = PI;
+        self
+    }
+
+    pub fn is_retrograde(&self) -> bool {
+        self.inclination > PI / 2.0
+    }
+
+    /// Sets the position along the orbit from a mean anomaly instead of the true anomaly.
+    pub fn at_mean_anomaly(mut self, mean_anomaly: f64) -> Self {
+        self.true_anomaly = true_from_mean(mean_anomaly, self.eccentricity);
+        self
+    }
+
+    /// Semi-latus rectum p = a(1 - e²).
+    pub fn semi_latus_rectum(&self) -> f64 {
+        self.semi_major_axis * (1.0 - self.eccentricity * self.eccentricity)
+    }
+
+    pub fn periapsis(&self) -> f64 {
+        self.semi_major_axis * (1.0 - self.eccentricity)
+    }
+
+    /// Apoapsis distance, infinite for unbound orbits.
+    pub fn apoapsis(&self) -> f64 {
+        if self.eccentricity < 1.0 {
+            self.semi_major_axis * (1.0 + self.eccentricity)
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Orbital period for gravitational parameter `mu` = G(M + m), infinite for unbound orbits.
+    pub fn period(&self, mu: f64) -> f64 {
+        if self.eccentricity < 1.0 {
+            2.0 * PI * (self.semi_major_axis.powi(3) / mu).sqrt()
+        } else {
+            f64::INFINITY
+        }
+    }
+
+    /// Eccentric anomaly E (elliptic) or hyperbolic anomaly H (e > 1).
+    pub fn eccentric_anomaly(&self) -> f64 {
+        eccentric_from_true(self.true_anomaly, self.eccentricity)
+    }
+
+    /// Mean anomaly M = E - e sin E (elliptic) or M = e sinh H - H (e > 1).
+    pub fn mean_anomaly(&self) -> f64 {
+        mean_from_eccentric(self.eccentric_anomaly(), self.eccentricity)
+    }
+
+    /// Longitude of periapsis ϖ, the direction of periapsis measured from +x in the plane.
+    pub fn longitude_of_periapsis(&self) -> f64 {
+        if self.is_retrograde() {
+            self.ascending_node - self.argument_of_periapsis
+        } else {
+            self.ascending_node + self.argument_of_periapsis
+        }
+    }
+
+    /// Elements of the orbit of a body at `position` with `velocity` relative to its parent.
+    pub fn from_state(position: Vector2<f64>, velocity: Vector2<f64>, mu: f64) -> Self {
+        // a retrograde orbit is a prograde one seen in the plane mirrored along the line of nodes
+        let retrograde = position.perp(&velocity) < 0.0;
+        let flip = |v: Vector2<f64>| if retrograde { Vector2::new(v.x, -v.y) } else { v };
+        let (r, v) = (flip(position), flip(velocity));
+
+        let distance = r.norm();
+        let energy = v.norm_squared() / 2.0 - mu / distance;
+        let eccentricity_vector = ((v.norm_squared() - mu / distance) * r - r.dot(&v) * v) / mu;
+        let eccentricity = eccentricity_vector.norm();
+
+        // circular orbits have no periapsis, measure from the node instead
+        let argument_of_periapsis = if eccentricity > 1e-12 {
+            eccentricity_vector.y.atan2(eccentricity_vector.x)
+        } else {
+            0.0
+        };
+        let true_anomaly = r.y.atan2(r.x) - argument_of_periapsis;
+
+        OrbitalElements {
+            semi_major_axis: -mu / (2.0 * energy),
+            eccentricity,
+            inclination: if retrograde { PI } else { 0.0 },
+            ascending_node: 0.0,
+            argument_of_periapsis: wrap_angle(argument_of_periapsis),
+            true_anomaly: wrap_angle(true_anomaly),
+        }
+    }
+
+    /// Position and velocity relative to the parent, for gravitational parameter `mu`.
+    pub fn to_state(self, mu: f64) -> (Vector2<f64>, Vector2<f64>) {
+        let (e, nu) = (self.eccentricity, self.true_anomaly);
+        let p = self.semi_latus_rectum();
+        let distance = p / (1.0 + e * nu.cos());
+
+        // perifocal frame, periapsis along +x
+        let r = Vector2::new(nu.cos(), nu.sin()) * distance;
+        let v = Vector2::new(-nu.sin(), e + nu.cos()) * (mu / p).sqrt();
+
+        let rotate = |v: Vector2<f64>, angle: f64| {
+            let (sin, cos) = angle.sin_cos();
+            Vector2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+        };
+        let orient = |v: Vector2<f64>| {
+            let v = rotate(v, self.argument_of_periapsis);
+            let v = if self.is_retrograde() { Vector2::new(v.x, -v.y) } else { v };
+            rotate(v, self.ascending_node)
+        };
+        (orient(r), orient(v))
+    }
+}
+
+impl CelestialObject {
+    /// A body on the orbit described by `elements` around `parent`, for gravitational constant `g`
+    /// in the units of `parent` (`objects::G` for SI, `Units::g` otherwise).
+    pub fn from_elements(name: String, mass: Mass, parent: &CelestialObject, elements: &OrbitalElements, g: f64) -> Self {
+        let (r, v) = elements.to_state(mu(g, parent.mass, mass));
+        let position: Position = parent.position + r;
+        let velocity: Velocity = parent.velocity + v;
+        CelestialObject::new(name, mass, position, velocity, Vector2::new(0.0, 0.0), position)
+    }
+
+    /// Elements of this body's orbit around `parent`, treating the pair as isolated.
+    pub fn elements_around(&self, parent: &CelestialObject, g: f64) -> OrbitalElements {
+        OrbitalElements::from_state(self.position - parent.position, self.velocity - parent.velocity, mu(g, parent.mass, self.mass))
+    }
+}
+
+/// Eccentric (e < 1) or hyperbolic (e > 1) anomaly from the true anomaly.
+pub fn eccentric_from_true(true_anomaly: f64, eccentricity: f64) -> f64 {
+    let e = eccentricity;
+    let half = true_anomaly / 2.0;
+    if e < 1.0 {
+        2.0 * (((1.0 - e) / (1.0 + e)).sqrt() * half.tan()).atan()
+    } else {
+        2.0 * (((e - 1.0) / (e + 1.0)).sqrt() * half.tan()).atanh()
+    }
+}
+
+/// True anomaly from the eccentric (e < 1) or hyperbolic (e > 1) anomaly.
+pub fn true_from_eccentric(eccentric_anomaly: f64, eccentricity: f64) -> f64 {
+    let e = eccentricity;
+    let half = eccentric_anomaly / 2.0;
+    if e < 1.0 {
+        2.0 * (((1.0 + e) / (1.0 - e)).sqrt() * half.tan()).atan()
+    } else {
+        2.0 * (((e + 1.0) / (e - 1.0)).sqrt() * half.tanh()).atan()
+    }
+}
+
+/// Kepler's equation: M = E - e sin E, or M = e sinh H - H for hyperbolic orbits.
+pub fn mean_from_eccentric(eccentric_anomaly: f64, eccentricity: f64) -> f64 {
+    if eccentricity < 1.0 {
+        eccentric_anomaly - eccentricity * eccentric_anomaly.sin()
+    } else {
+        eccentricity * eccentric_anomaly.sinh() - eccentric_anomaly
+    }
+}
+
+/// Solves Kepler's equation for E (or H) by Newton's method.
+pub fn eccentric_from_mean(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let e = eccentricity;
+    if e < 1.0 {
+        let m = wrap_angle(mean_anomaly);
+        let mut anomaly = if e > 0.8 { PI.copysign(m) } else { m };
+        for _ in 0..50 {
+            let delta = (anomaly - e * anomaly.sin() - m) / (1.0 - e * anomaly.cos());
+            anomaly -= delta;
+            if delta.abs() < 1e-14 {
+                break;
+            }
+        }
+        anomaly
+    } else {
+        let m = mean_anomaly;
+        let mut anomaly = (2.0 * m / e).asinh();
+        for _ in 0..50 {
+            let delta = (e * anomaly.sinh() - anomaly - m) / (e * anomaly.cosh() - 1.0);
+            anomaly -= delta;
+            if delta.abs() < 1e-14 {
+                break;
+            }
+        }
+        anomaly
+    }
+}
+
+/// True anomaly from the mean anomaly, solving Kepler's equation.
+pub fn true_from_mean(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    true_from_eccentric(eccentric_from_mean(mean_anomaly, eccentricity), eccentricity)
+}
+
+/// Wraps an angle into (-π, π].
+pub fn wrap_angle(angle: f64) -> f64 {
+    let wrapped = (angle + PI).rem_euclid(2.0 * PI) - PI;
+    if wrapped == -PI { PI } else { wrapped }
+}
+
+/// Gravitational parameter of a pair, for gravitational constant `g`.
+pub fn mu(g: f64, primary: Mass, secondary: Mass) -> f64 {
+    g * (primary + secondary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vector2<f64>, b: Vector2<f64>) -> bool {
+        (a - b).norm() <= 1e-12 * b.norm().max(1.0)
+    }
+
+    #[test]
+    fn state_elements_state() {
+        let mu = 1.0;
+        let states = [
+            ("circular", Vector2::new(1.0, 0.0), Vector2::new(0.0, 1.0)),
+            ("elliptical", Vector2::new(0.3, -1.2), Vector2::new(0.8, 0.4)),
+            ("retrograde", Vector2::new(0.3, -1.2), Vector2::new(-0.8, -0.4)),
+            ("hyperbolic", Vector2::new(1.0, 0.5), Vector2::new(0.5, 1.8)),
+        ];
+        for (kind, position, velocity) in states {
+            let elements = OrbitalElements::from_state(position, velocity, mu);
+            let (e, a) = (elements.eccentricity, elements.semi_major_axis);
+            match kind {
+                "circular" => assert!(e < 1e-12 && (a - 1.0).abs() < 1e-12, "{elements:?}"),
+                "hyperbolic" => assert!(e > 1.0 && a < 0.0, "{elements:?}"),
+                _ => assert!(e > 0.0 && e < 1.0 && a > 0.0, "{elements:?}"),
+            }
+            assert_eq!(elements.is_retrograde(), kind == "retrograde");
+            let (r, v) = elements.to_state(mu);
+            assert!(close(r, position) && close(v, velocity), "{kind}: {r:?} {v:?}");
+        }
+    }
+
+    #[test]
+    fn elements_state_elements() {
+        let mu = 2.5;
+        for elements in [
+            OrbitalElements::new(1.5, 0.0, 0.0, 1.0),
+            OrbitalElements::new(1.5, 0.3, 1.0, 2.0),
+            OrbitalElements::new(0.7, 0.95, -2.0, 3.0).retrograde(),
+            OrbitalElements::new(-2.0, 1.5, 0.5, -1.5),
+        ] {
+            let (r, v) = elements.to_state(mu);
+            let back = OrbitalElements::from_state(r, v, mu);
+            assert!((back.semi_major_axis - elements.semi_major_axis).abs() < 1e-12 * elements.semi_major_axis.abs(), "{back:?}");
+            assert!((back.eccentricity - elements.eccentricity).abs() < 1e-12, "{back:?}");
+            assert_eq!(back.inclination, elements.inclination);
+            // only the sum is defined on a circle
+            let longitude = |e: &OrbitalElements| e.argument_of_periapsis + e.true_anomaly;
+            assert!(wrap_angle(longitude(&back) - longitude(&elements)).abs() < 1e-12, "{back:?}");
+            if elements.eccentricity > 0.0 {
+                assert!(wrap_angle(back.true_anomaly - elements.true_anomaly).abs() < 1e-12, "{back:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn anomaly_conversions_invert() {
+        for eccentricity in [0.0, 0.3, 0.9, 0.99, 1.5, 4.0] {
+            for mean_anomaly in [-3.0, -1.0, 0.0, 0.2, 2.5] {
+                let eccentric = eccentric_from_mean(mean_anomaly, eccentricity);
+                let back = mean_from_eccentric(eccentric, eccentricity);
+                assert!((back - mean_anomaly).abs() < 1e-12, "e {eccentricity}, M {mean_anomaly}: {back}");
+            }
+            // within the asymptotes of a hyperbola
+            let limit = if eccentricity < 1.0 { PI } else { (-1.0 / eccentricity).acos() };
+            for true_anomaly in [-0.9, -0.5, 0.0, 0.3, 0.8].map(|fraction| fraction * limit) {
+                let back = true_from_eccentric(eccentric_from_true(true_anomaly, eccentricity), eccentricity);
+                assert!((back - true_anomaly).abs() < 1e-12, "e {eccentricity}, ν {true_anomaly}: {back}");
+            }
+        }
+    }
+}
@@ -0,0 +1,525 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use nalgebra::{Point2, Vector2};
+use serde_json::{json, Map, Value};
+
+use crate::engine::events::Watch;
+use crate::engine::integrator::{self, Integrator};
+use crate::engine::solver::SolverSpec;
+use crate::engine::timestep::{BlockTimesteps, Criterion};
+use crate::space::frame::Frame;
+use crate::space::objects::{BodyKind, CelestialObject, G};
+use crate::space::orbits::OrbitalElements;
+use crate::space::system::StarSystem;
+use crate::space::units::Units;
+
+/// Version written by `save`; files of any other version are rejected.
+pub const VERSION: u64 = 1;
+
+/// A system ready to run, read from a scenario file.
+///
+/// A scenario is a JSON object:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "units": "astronomical",
+///   "G": 2.959122e-4,
+///   "solver": { "name": "barnes-hut", "theta": 0.5 },
+///   "integrator": "verlet",
+///   "dt": 0.1,
+///   "bodies": [
+///     { "name": "Sun", "mass": 1.0, "position": [0, 0], "velocity": [0, 0], "radius": 0.00465, "colour": "#ffd700" },
+///     { "name": "Earth", "mass": 3.0e-6, "position": [1, 0], "velocity": [0, 0.0172] }
+///   ]
+/// }
+/// ```
+///
+/// `units` is "SI", "astronomical", "henon" (G = 1 for a solar mass within an AU, see
+/// `Units::henon_solar`) or `{ "length", "time", "mass" }` in SI, and every number in
+/// the file is in these units. `G` is optional; given without `units` it picks units of meters and
+/// seconds with the mass unit that makes G this value, given with them it must agree with them to
+/// 0.1%, as published values of the constants and the solar mass differ a little.
+/// `solver` is "direct", "barnes-hut" (with a top level `theta`) or an object with `name` and its
+/// parameters, "1pn" wrapping a `newtonian` solver. Bodies may have a `radius`, a `kind` out of
+/// "regular", "black_hole" and "test_particle", a `colour` as "#rrggbb" and their own `softening`.
+/// Black holes without a radius get their event horizon, test particles always have zero mass.
+///
+/// Instead of `position` and `velocity` a body may give an `orbit` around a body listed before it:
+/// `{ "parent": "Sun", "semi_major_axis": 1.52, "eccentricity": 0.093, "periapsis": 5.0, "mean_anomaly": 0.3 }`,
+/// with the argument of `periapsis` and the anomaly, `mean_anomaly` or `true_anomaly`, in radians
+/// (both 0 if left out) and `"retrograde": true` for a clockwise orbit.
+/// `softening` and the start `time` of the system are optional too.
+///
+/// `timesteps` turns on per-body block timesteps, see `StarSystem::with_block_timesteps`:
+/// `{ "criterion": "jerk", "eta": 0.005, "max_level": 8 }`, or `"criterion": "acceleration"` with an
+/// `eta` and a `length`. `max_level` is 8 if left out.
+///
+/// `trojans` scatters test particles around L4 and L5 of pairs of bodies, see `StarSystem::add_trojans`:
+/// `[{ "primary": "Sun", "secondary": "Jupiter", "per_point": 50, "spread": 0.05 }]`. They are
+/// added after the bodies and saved as bodies of their own.
+///
+/// `watch` lists the events to detect, see `engine::events`: "apsides", "escapes",
+/// `{ "close_approach": 1e-3 }` with a distance, and `{ "alignments": "Earth" }` or
+/// `{ "transits": "Earth" }` with the observer.
+///
+/// `frame` is the frame the viewer and the recorder show the bodies in, see `Frame`: "inertial"
+/// (the default), "barycentric", `{ "body": "Earth" }` or `{ "corotating": ["Sun", "Jupiter"] }`.
+pub struct Scenario {
+    pub system: StarSystem,
+    pub integrator: Box<dyn Integrator>,
+    pub dt: f64,
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn number(value: &Value, what: &str) -> io::Result<f64> {
+    value.as_f64().ok_or_else(|| invalid(format!("{what} must be a number")))
+}
+
+fn optional_number(object: &Map<String, Value>, key: &str, what: &str) -> io::Result<Option<f64>> {
+    object.get(key).map(|value| number(value, &format!("{what} {key}"))).transpose()
+}
+
+fn vector(value: Option<&Value>, what: &str) -> io::Result<Vector2<f64>> {
+    match value.and_then(Value::as_array).map(Vec::as_slice) {
+        Some([x, y]) => Ok(Vector2::new(number(x, what)?, number(y, what)?)),
+        _ => Err(invalid(format!("{what} must be a pair of numbers [x, y]"))),
+    }
+}
+
+fn units(value: &Value) -> io::Result<Units> {
+    match value {
+        Value::String(name) => match name.to_lowercase().as_str() {
+            "si" => Ok(Units::SI),
+            "astronomical" | "au/day/msun" => Ok(Units::ASTRONOMICAL),
+            "henon" => Ok(Units::henon_solar()),
+            _ => Err(invalid(format!("unknown units \"{name}\""))),
+        },
+        Value::Object(object) => {
+            let unit = |key: &str| -> io::Result<f64> {
+                let value = optional_number(object, key, "units")?.ok_or_else(|| invalid(format!("units need a {key}")))?;
+                if value > 0.0 { Ok(value) } else { Err(invalid(format!("units {key} must be positive"))) }
+            };
+            Ok(Units { length: unit("length")?, time: unit("time")?, mass: unit("mass")? })
+        }
+        _ => Err(invalid("units must be a name or { \"length\", \"time\", \"mass\" }")),
+    }
+}
+
+fn units_json(units: &Units) -> Value {
+    if *units == Units::SI {
+        json!("SI")
+    } else if *units == Units::ASTRONOMICAL {
+        json!("astronomical")
+    } else if *units == Units::henon_solar() {
+        json!("henon")
+    } else {
+        json!({ "length": units.length, "time": units.time, "mass": units.mass })
+    }
+}
+
+fn solver(value: &Value, theta: Option<f64>) -> io::Result<SolverSpec> {
+    let (name, object) = match value {
+        Value::String(name) => (name.as_str(), None),
+        Value::Object(object) => match object.get("name").and_then(Value::as_str) {
+            Some(name) => (name, Some(object)),
+            None => return Err(invalid("solver needs a name")),
+        },
+        _ => return Err(invalid("solver must be a name or an object with a name")),
+    };
+    let parameter = |key: &str| match object {
+        Some(object) => optional_number(object, key, "solver"),
+        None => Ok(None),
+    };
+    match name {
+        "direct" => Ok(SolverSpec::Direct),
+        "barnes-hut" => Ok(SolverSpec::BarnesHut {
+            theta: parameter("theta")?.or(theta).unwrap_or(0.5),
+            capacity: parameter("capacity")?.map_or(4, |capacity| capacity.max(1.0) as usize),
+        }),
+        "1pn" => {
+            let newtonian = match object.and_then(|object| object.get("newtonian")) {
+                Some(newtonian) => solver(newtonian, theta)?,
+                None => SolverSpec::Direct,
+            };
+            Ok(SolverSpec::PostNewtonian(Box::new(newtonian)))
+        }
+        _ => Err(invalid(format!("unknown solver \"{name}\""))),
+    }
+}
+
+fn solver_json(spec: &SolverSpec) -> Value {
+    match spec {
+        SolverSpec::Direct => json!("direct"),
+        SolverSpec::BarnesHut { theta, capacity } => json!({ "name": "barnes-hut", "theta": theta, "capacity": capacity }),
+        SolverSpec::PostNewtonian(newtonian) => json!({ "name": "1pn", "newtonian": solver_json(newtonian) }),
+    }
+}
+
+fn timesteps(value: &Value) -> io::Result<BlockTimesteps> {
+    let object = value.as_object().ok_or_else(|| invalid("timesteps must be an object"))?;
+    let parameter = |key: &str| optional_number(object, key, "timesteps")?.ok_or_else(|| invalid(format!("timesteps need a {key}")));
+    let criterion = match object.get("criterion").and_then(Value::as_str) {
+        Some("acceleration") => Criterion::Acceleration { eta: parameter("eta")?, length: parameter("length")? },
+        Some("jerk") => Criterion::Jerk { eta: parameter("eta")? },
+        _ => return Err(invalid("timesteps criterion must be acceleration or jerk")),
+    };
+    let max_level = optional_number(object, "max_level", "timesteps")?.unwrap_or(8.0);
+    if !(0.0..=30.0).contains(&max_level) {
+        return Err(invalid("timesteps max_level must be between 0 and 30"));
+    }
+    Ok(BlockTimesteps::new(criterion, max_level as u32))
+}
+
+fn timesteps_json(timesteps: &BlockTimesteps) -> Value {
+    match timesteps.criterion {
+        Criterion::Acceleration { eta, length } => json!({ "criterion": "acceleration", "eta": eta, "length": length, "max_level": timesteps.max_level }),
+        Criterion::Jerk { eta } => json!({ "criterion": "jerk", "eta": eta, "max_level": timesteps.max_level }),
+    }
+}
+
+fn add_trojans(system: &mut StarSystem, value: &Value) -> io::Result<()> {
+    let object = value.as_object().ok_or_else(|| invalid("every trojans entry must be an object"))?;
+    let name = |key: &str| object.get(key).and_then(Value::as_str).ok_or_else(|| invalid(format!("trojans need a {key}")));
+    let (primary, secondary) = (name("primary")?, name("secondary")?);
+    let per_point = optional_number(object, "per_point", "trojans")?.ok_or_else(|| invalid("trojans need a per_point count"))?;
+    let spread = optional_number(object, "spread", "trojans")?.unwrap_or(0.05);
+    if per_point >= 1.0 && system.add_trojans(primary, secondary, per_point as usize, spread) == 0 {
+        return Err(invalid(format!("trojans of {primary} and {secondary}: both must be in the scenario")));
+    }
+    Ok(())
+}
+
+fn watch(value: &Value, bodies: &[CelestialObject]) -> io::Result<Watch> {
+    let observer = |value: &Value| -> io::Result<String> {
+        let name = value.as_str().ok_or_else(|| invalid("an observer must be a name"))?;
+        if bodies.iter().any(|body| body.name == name) {
+            Ok(name.to_string())
+        } else {
+            Err(invalid(format!("observer {name} is not in the scenario")))
+        }
+    };
+    match value {
+        Value::String(name) if name == "apsides" => Ok(Watch::Apsides),
+        Value::String(name) if name == "escapes" => Ok(Watch::Escapes),
+        Value::Object(object) if object.len() == 1 => match object.iter().next() {
+            Some((key, threshold)) if key == "close_approach" => Ok(Watch::CloseApproach { threshold: number(threshold, "close_approach")? }),
+            Some((key, name)) if key == "alignments" => Ok(Watch::Alignments { observer: observer(name)? }),
+            Some((key, name)) if key == "transits" => Ok(Watch::Transits { observer: observer(name)? }),
+            _ => Err(invalid("watch must be close_approach, alignments or transits")),
+        },
+        _ => Err(invalid("watch must be apsides, escapes or an object with close_approach, alignments or transits")),
+    }
+}
+
+fn watch_json(watch: &Watch) -> Value {
+    match watch {
+        Watch::Apsides => json!("apsides"),
+        Watch::Escapes => json!("escapes"),
+        Watch::CloseApproach { threshold } => json!({ "close_approach": threshold }),
+        Watch::Alignments { observer } => json!({ "alignments": observer }),
+        Watch::Transits { observer } => json!({ "transits": observer }),
+    }
+}
+
+fn frame(value: &Value, bodies: &[CelestialObject]) -> io::Result<Frame> {
+    let name = |value: &Value| -> io::Result<String> {
+        let name = value.as_str().ok_or_else(|| invalid("frame bodies must be names"))?;
+        if bodies.iter().any(|body| body.name == name) {
+            Ok(name.to_string())
+        } else {
+            Err(invalid(format!("frame body {name} is not in the scenario")))
+        }
+    };
+    match value {
+        Value::String(text) if text == "inertial" => Ok(Frame::Inertial),
+        Value::String(text) if text == "barycentric" => Ok(Frame::Barycentric),
+        Value::Object(object) => match (object.get("body"), object.get("corotating").and_then(Value::as_array).map(Vec::as_slice)) {
+            (Some(body), None) => Ok(Frame::Body(name(body)?)),
+            (None, Some([a, b])) => Ok(Frame::Corotating(name(a)?, name(b)?)),
+            _ => Err(invalid("frame must have a body or a corotating pair of bodies")),
+        },
+        _ => Err(invalid("frame must be inertial, barycentric, { \"body\" } or { \"corotating\" }")),
+    }
+}
+
+fn frame_json(frame: &Frame) -> Value {
+    match frame {
+        Frame::Inertial => json!("inertial"),
+        Frame::Barycentric => json!("barycentric"),
+        Frame::Body(name) => json!({ "body": name }),
+        Frame::Corotating(a, b) => json!({ "corotating": [a, b] }),
+    }
+}
+
+fn colour(value: &Value) -> io::Result<[u8; 3]> {
+    let hex = value.as_str()
+        .and_then(|text| text.strip_prefix('#'))
+        .filter(|hex| hex.len() == 6 && hex.is_ascii());
+    let channel = |i: usize| hex.and_then(|hex| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).ok());
+    match (channel(0), channel(1), channel(2)) {
+        (Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(invalid(format!("colour {value} must look like \"#rrggbb\""))),
+    }
+}
+
+/// Position and velocity of a body of `mass` on the `orbit` of a scenario body, see `Scenario`.
+fn orbit(value: &Value, name: &str, mass: f64, bodies: &[CelestialObject], units: &Units) -> io::Result<(Point2<f64>, Vector2<f64>)> {
+    let object = value.as_object().ok_or_else(|| invalid(format!("orbit of {name} must be an object")))?;
+    let what = format!("orbit of {name}");
+    let parent = object.get("parent")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid(format!("{what} needs a parent")))?;
+    let parent = bodies.iter()
+        .find(|body| body.name == parent)
+        .ok_or_else(|| invalid(format!("parent {parent} of {name} must be listed before it")))?;
+    let semi_major_axis = optional_number(object, "semi_major_axis", &what)?.ok_or_else(|| invalid(format!("{what} needs a semi_major_axis")))?;
+    let eccentricity = optional_number(object, "eccentricity", &what)?.unwrap_or(0.0);
+    if eccentricity < 0.0 || eccentricity == 1.0 || (eccentricity < 1.0) != (semi_major_axis > 0.0) {
+        return Err(invalid(format!("{what}: an ellipse needs 0 <= e < 1 and a > 0, a hyperbola e > 1 and a < 0")));
+    }
+    let periapsis = optional_number(object, "periapsis", &what)?.unwrap_or(0.0);
+
+    let mut elements = OrbitalElements::new(semi_major_axis, eccentricity, periapsis, 0.0);
+    match (optional_number(object, "mean_anomaly", &what)?, optional_number(object, "true_anomaly", &what)?) {
+        (Some(_), Some(_)) => return Err(invalid(format!("{what} takes a mean_anomaly or a true_anomaly, not both"))),
+        (Some(mean_anomaly), None) => elements = elements.at_mean_anomaly(mean_anomaly),
+        (None, true_anomaly) => elements.true_anomaly = true_anomaly.unwrap_or(0.0),
+    }
+    if object.get("retrograde").and_then(Value::as_bool).unwrap_or(false) {
+        elements = elements.retrograde();
+    }
+    let body = CelestialObject::from_elements(name.to_string(), mass, parent, &elements, units.g());
+    Ok((body.position, body.velocity))
+}
+
+fn body(value: &Value, bodies: &[CelestialObject], units: &Units) -> io::Result<CelestialObject> {
+    let object = value.as_object().ok_or_else(|| invalid("every body must be an object"))?;
+    let name = object.get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("every body needs a name"))?
+        .to_string();
+    let what = |field: &str| format!("{field} of {name}");
+    let kind = match object.get("kind").map(|kind| kind.as_str()) {
+        None | Some(Some("regular")) => BodyKind::Regular,
+        Some(Some("black_hole")) => BodyKind::BlackHole,
+        Some(Some("test_particle")) => BodyKind::TestParticle,
+        Some(_) => return Err(invalid(format!("{} must be regular, black_hole or test_particle", what("kind")))),
+    };
+    let mass = match (kind, object.get("mass")) {
+        (BodyKind::TestParticle, _) => 0.0,
+        (_, Some(mass)) => number(mass, &what("mass"))?,
+        (_, None) => return Err(invalid(format!("{name} needs a mass"))),
+    };
+    let (position, velocity) = match object.get("orbit") {
+        Some(value) => orbit(value, &name, mass, bodies, units)?,
+        None => (Point2::from(vector(object.get("position"), &what("position"))?), vector(object.get("velocity"), &what("velocity"))?),
+    };
+
+    let mut body = match kind {
+        BodyKind::BlackHole => CelestialObject::black_hole(name.clone(), mass, position, velocity, units.gravity(0.0)),
+        BodyKind::TestParticle => CelestialObject::test_particle(name.clone(), position, velocity),
+        BodyKind::Regular => CelestialObject::new(name.clone(), mass, position, velocity, Vector2::new(0.0, 0.0), position),
+    };
+    if let Some(radius) = optional_number(object, "radius", &name)? {
+        body.radius = radius;
+    }
+    body.softening = optional_number(object, "softening", &name)?;
+    body.colour = object.get("colour").map(colour).transpose()?;
+    Ok(body)
+}
+
+fn body_json(body: &CelestialObject) -> Value {
+    let mut object = Map::new();
+    object.insert("name".into(), json!(body.name));
+    if !body.is_test_particle() {
+        object.insert("mass".into(), json!(body.mass));
+    }
+    object.insert("position".into(), json!([body.position.x, body.position.y]));
+    object.insert("velocity".into(), json!([body.velocity.x, body.velocity.y]));
+    if body.radius > 0.0 {
+        object.insert("radius".into(), json!(body.radius));
+    }
+    match body.kind {
+        BodyKind::Regular => {}
+        BodyKind::BlackHole => {
+            object.insert("kind".into(), json!("black_hole"));
+        }
+        BodyKind::TestParticle => {
+            object.insert("kind".into(), json!("test_particle"));
+        }
+    }
+    if let Some([r, g, b]) = body.colour {
+        object.insert("colour".into(), json!(format!("#{r:02x}{g:02x}{b:02x}")));
+    }
+    if let Some(softening) = body.softening {
+        object.insert("softening".into(), json!(softening));
+    }
+    Value::Object(object)
+}
+
+impl Scenario {
+    pub fn from_json(value: &Value) -> io::Result<Self> {
+        let object = value.as_object().ok_or_else(|| invalid("a scenario must be a JSON object"))?;
+        match object.get("version").and_then(Value::as_u64) {
+            Some(VERSION) => {}
+            Some(version) => return Err(invalid(format!("scenario version {version} is not supported, expected {VERSION}"))),
+            None => return Err(invalid("a scenario needs a version")),
+        }
+
+        let g = optional_number(object, "G", "scenario")?;
+        let units = match (object.get("units"), g) {
+            (Some(value), _) => units(value)?,
+            (None, Some(g)) if g > 0.0 => Units { length: 1.0, time: 1.0, mass: g / G },
+            (None, Some(_)) => return Err(invalid("G must be positive")),
+            (None, None) => Units::SI,
+        };
+        if let Some(g) = g {
+            if ((g - units.g()) / units.g()).abs() > 1e-3 {
+                return Err(invalid(format!("G = {g} does not match the units, in which it is {}", units.g())));
+            }
+        }
+
+        let theta = optional_number(object, "theta", "scenario")?;
+        let spec = match object.get("solver") {
+            Some(value) => solver(value, theta)?,
+            None => SolverSpec::BarnesHut { theta: theta.unwrap_or(0.5), capacity: 4 },
+        };
+        let integrator = match object.get("integrator") {
+            Some(value) => {
+                let name = value.as_str().ok_or_else(|| invalid("integrator must be a name"))?;
+                integrator::by_name(name).ok_or_else(|| invalid(format!("unknown integrator \"{name}\"")))?
+            }
+            None => Box::new(integrator::VelocityVerlet),
+        };
+        let dt = optional_number(object, "dt", "scenario")?.ok_or_else(|| invalid("a scenario needs a dt"))?;
+        if dt <= 0.0 {
+            return Err(invalid("dt must be positive"));
+        }
+
+        let mut system = StarSystem::new().with_solver(spec.build());
+        system.units = units;
+        system.softening = optional_number(object, "softening", "scenario")?.unwrap_or(0.0);
+        system.time = optional_number(object, "time", "scenario")?.unwrap_or(0.0);
+        system.timesteps = object.get("timesteps").map(timesteps).transpose()?;
+        let bodies = object.get("bodies").and_then(Value::as_array).ok_or_else(|| invalid("a scenario needs a list of bodies"))?;
+        for value in bodies {
+            let body = body(value, &system.bodies, &units)?;
+            system.add_body(body);
+        }
+        if let Some(trojans) = object.get("trojans") {
+            for value in trojans.as_array().ok_or_else(|| invalid("trojans must be a list"))? {
+                add_trojans(&mut system, value)?;
+            }
+        }
+        if let Some(watches) = object.get("watch") {
+            for value in watches.as_array().ok_or_else(|| invalid("watch must be a list"))? {
+                let watch = watch(value, &system.bodies)?;
+                system.watch(watch);
+            }
+        }
+        if let Some(value) = object.get("frame") {
+            system.frame = frame(value, &system.bodies)?;
+        }
+        Ok(Scenario { system, integrator, dt })
+    }
+
+    pub fn to_json(system: &StarSystem, integrator: &dyn Integrator, dt: f64) -> Value {
+        let mut value = json!({
+            "version": VERSION,
+            "units": units_json(&system.units),
+            "G": system.units.g(),
+            "solver": solver_json(&system.solver.spec()),
+            "integrator": integrator.name(),
+            "dt": dt,
+            "softening": system.softening,
+            "time": system.time,
+            "bodies": system.bodies.iter().map(body_json).collect::<Vec<_>>(),
+        });
+        if let Some(timesteps) = &system.timesteps {
+            value["timesteps"] = timesteps_json(timesteps);
+        }
+        if let Some(detector) = system.detector.as_ref().filter(|detector| !detector.watches.is_empty()) {
+            value["watch"] = json!(detector.watches.iter().map(watch_json).collect::<Vec<_>>());
+        }
+        if system.frame != Frame::Inertial {
+            value["frame"] = frame_json(&system.frame);
+        }
+        value
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let value: Value = serde_json::from_str(&text).map_err(|error| invalid(error.to_string()))?;
+        Scenario::from_json(&value)
+    }
+
+    /// Writes `system` as a scenario. Numbers are written in full, so loading it back gives the same bits.
+    pub fn save(system: &StarSystem, path: impl AsRef<Path>, integrator: &dyn Integrator, dt: f64) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(&Scenario::to_json(system, integrator, dt)).map_err(|error| invalid(error.to_string()))?;
+        fs::write(path, text + "\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let scenario = Scenario::from_json(&json!({
+            "version": 1,
+            "units": "astronomical",
+            "solver": { "name": "barnes-hut", "theta": 0.7 },
+            "integrator": "yoshida4",
+            "dt": 0.01,
+            "softening": 1e-4,
+            "time": 12.5,
+            "bodies": [
+                { "name": "Sun", "mass": 1.0, "position": [0, 0], "velocity": [0, 0], "colour": "#ffcc33" },
+                { "name": "Jupiter", "mass": 9.5e-4, "orbit": { "parent": "Sun", "semi_major_axis": 5.2, "eccentricity": 0.048, "mean_anomaly": 0.3 } },
+                { "name": "Probe", "mass": 1.0, "kind": "test_particle", "position": [1, 0.1], "velocity": [0, 0.0172] }
+            ],
+            "timesteps": { "criterion": "jerk", "eta": 0.005, "max_level": 6 },
+            "trojans": [{ "primary": "Sun", "secondary": "Jupiter", "per_point": 3 }],
+            "watch": ["apsides", { "close_approach": 0.01 }, { "transits": "Probe" }],
+            "frame": { "corotating": ["Sun", "Jupiter"] }
+        }))
+        .unwrap();
+        let saved = Scenario::to_json(&scenario.system, scenario.integrator.as_ref(), scenario.dt);
+
+        let path = std::env::temp_dir().join(format!("gravity-round-trip-{}.json", std::process::id()));
+        scenario.system.save(&path, scenario.integrator.as_ref(), scenario.dt).unwrap();
+        let (loaded, system) = (Scenario::load(&path), StarSystem::load(&path));
+        fs::remove_file(&path).unwrap();
+        let (loaded, system) = (loaded.unwrap(), system.unwrap());
+
+        assert_eq!(Scenario::to_json(&loaded.system, loaded.integrator.as_ref(), loaded.dt), saved);
+        assert_eq!(loaded.system.bodies.len(), 3 + 2 * 3);
+        for (a, b) in scenario.system.bodies.iter().zip(&loaded.system.bodies) {
+            assert_eq!((a.mass, a.position, a.velocity), (b.mass, b.position, b.velocity), "{}", a.name);
+        }
+        assert_eq!(loaded.system.frame, scenario.system.frame);
+        assert_eq!(loaded.system.time, 12.5);
+        assert!(system.bodies.iter().zip(&loaded.system.bodies).all(|(a, b)| a.position == b.position && a.velocity == b.velocity));
+    }
+
+    #[test]
+    fn henon_units_by_name() {
+        let scenario = Scenario::from_json(&json!({
+            "version": 1,
+            "units": "henon",
+            "G": 1.0,
+            "dt": 0.01,
+            "bodies": [{ "name": "Star", "mass": 1.0, "position": [0, 0], "velocity": [0, 0] }]
+        }))
+        .unwrap();
+        assert!((scenario.system.units.g() - 1.0).abs() < 1e-12);
+        assert_eq!(Scenario::to_json(&scenario.system, scenario.integrator.as_ref(), scenario.dt)["units"], json!("henon"));
+    }
+}
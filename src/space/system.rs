@@ -1,87 +1,394 @@
-use nalgebra::{Point2, Vector2};
+use std::io;
+use std::path::Path;
 
-use crate::space::objects::CelestialObject;
-use crate::engine::physics;
+use nalgebra::{Point2, Vector2};
 
-pub struct Star;
-pub struct Planet;
-pub struct Moon;
+use crate::space::frame::{self, Frame};
+use crate::space::hierarchy::Hierarchy;
+use crate::space::kepler;
+use crate::space::lagrange::{self, LagrangePoint};
+use crate::space::orbits::OrbitalElements;
+use crate::space::scenario::Scenario;
+use crate::space::objects::{CelestialObject, Gravity};
+use crate::space::units::Units;
+use crate::engine::integrator::{self, Integrator};
+use crate::engine::collision::{self, Collision, CollisionPolicy};
+use crate::engine::diagnostics::Diagnostics;
+use crate::engine::events::{Event, EventDetector, Watch};
+use crate::engine::physics::QuadTree;
+use crate::engine::solver::{BarnesHut, ForceSolver};
+use crate::engine::timestep::{BlockTimesteps, Criterion};
+use crate::gen::recorder::Recorder;
 
 /// A trait for systems that can be simulated.
 pub trait Simulatable {
     /// A function to get the bodies of the system
     fn bodies(&self) -> &Vec<CelestialObject>;
     /// A function to take control over the simulation by providing number of steps
-    fn simulate(&mut self, dt: f64, num_steps: usize);
+    fn simulate(&mut self, integrator: &dyn Integrator, dt: f64, num_steps: usize);
+    /// Simulated time since the start
+    fn time(&self) -> f64 {
+        0.0
+    }
+    /// G, c and the Plummer softening length in the system's units, used for the potential energy
+    fn gravity(&self) -> Gravity {
+        Gravity::default()
+    }
+    /// Units the bodies and time are given in
+    fn units(&self) -> Units {
+        Units::SI
+    }
+    /// Diagnostics at t=0, which drifts are reported against
+    fn baseline(&self) -> Option<&Diagnostics> {
+        None
+    }
+    /// Print the state every this many steps of `run`, 0 to stay quiet
+    fn report_every(&self) -> usize {
+        0
+    }
+
+    /// Energy, momenta and virial ratio of the current state, with the exact potential.
+    fn diagnostics(&self) -> Diagnostics {
+        Diagnostics::measure(self.bodies(), self.time(), self.gravity())
+    }
 
     /// Runs the star system simulation.
     ///
-    /// This method simulates the star system for a specified number of time steps,
-    /// using a variable time step to maintain a maximum acceleration tolerance.
+    /// This method simulates the star system for `num_steps` steps of `dt`, printing the state
+    /// every `report_every` steps.
     ///
     /// # Parameters
     ///
-    /// * `max_acceleration_tolerance`: The maximum acceleration tolerance for the simulation.
-    ///   A smaller value will result in a more accurate simulation, but may slow down the simulation.
-    ///   A larger value will result in a faster simulation, but may sacrifice some accuracy.
+    /// * `integrator`: The scheme used to advance the bodies, see `engine::integrator`.
+    ///   Higher order integrators are more accurate per step but evaluate the forces more often.
+    /// * `dt`: The time step. With block timesteps enabled (`StarSystem::with_block_timesteps`) this is
+    ///   the longest step, and bodies on short orbits such as moons take power-of-two substeps of it.
     /// * `num_steps`: The number of time steps to simulate.
-    ///   A smaller value will result in a shorter simulation, which may not capture the full dynamics of the system.
-    ///   A larger value will result in a longer simulation, which may capture more of the system's behavior.
     ///
     /// # Example
     ///
     /// ```
-    /// system.run(1.0, 1000);
+    /// # use gravity::engine::integrator::VelocityVerlet;
+    /// # use gravity::space::system::{Simulatable, StarSystem};
+    /// let mut system = StarSystem::solar();
+    /// system.run(&VelocityVerlet, 3600.0, 1000);
     /// ```
-    fn run(&mut self, max_acceleration_tolerance: f64, num_steps: usize) {
-        let mut dt = 1.0; // initial time step
-        let mut max_acceleration = 0.0; // initial maximum acceleration
+    fn run(&mut self, integrator: &dyn Integrator, dt: f64, num_steps: usize) {
+        for step in 1..=num_steps {
+            self.simulate(integrator, dt, 1);
 
-        for _ in 0..num_steps {
-            // Calculate the maximum acceleration of the bodies
-            for body in self.bodies() {
-                let acceleration = body.acceleration.norm();
-                if acceleration > max_acceleration {
-                    max_acceleration = acceleration;
-                }
-            }
-            // Adjust the time step based on the maximum acceleration
-            if max_acceleration > max_acceleration_tolerance {
-                dt *= 0.5; // reduce the time step if the acceleration is too high
-            } else {
-                dt *= 1.1; // increase the time step if the acceleration is low
+            if self.report_every() > 0 && step % self.report_every() == 0 {
+                self.state();
             }
-            // Simulate the next time step
-            self.simulate(dt, 1);
         }
     }
 
     /// A function to log the final state of the system.
     fn state(&self) {
-        println!("State of the system:");
+        println!("State of the system ({} units):", self.units().name());
         for body in self.bodies() {
             println!("  {}:", body.name);
             println!("    Position: ({:.2}, {:.2})", body.position.x, body.position.y);
             println!("    Velocity: ({:.2}, {:.2})", body.velocity.x, body.velocity.y);
         }
+
+        let diagnostics = self.diagnostics();
+        println!("  t = {:.2}", diagnostics.time);
+        println!("    Energy: {:.6e} (kinetic {:.6e}, potential {:.6e})", diagnostics.energy(), diagnostics.kinetic, diagnostics.potential);
+        println!("    Momentum: ({:.6e}, {:.6e}), angular momentum: {:.6e}", diagnostics.momentum.x, diagnostics.momentum.y, diagnostics.angular_momentum);
+        println!("    Virial ratio 2K/|U|: {:.4}", diagnostics.virial_ratio);
+        if let Some(baseline) = self.baseline() {
+            let drift = diagnostics.drift(baseline);
+            println!("    Drift since t=0: energy {:.3e}, momentum {:.3e}, angular momentum {:.3e}", drift.energy, drift.momentum, drift.angular_momentum);
+        }
     }
 }
 
+/// Called with every event as it is detected, see `StarSystem::on_event`.
+pub type EventCallback = Box<dyn FnMut(&Event)>;
+
+/// Steps between two measurements of `StarSystem::latest` unless set otherwise: the exact
+/// potential is O(n²), more than a step costs for large systems.
+pub const MEASURE_EVERY: usize = 100;
+
 pub struct StarSystem {
     pub bodies: Vec<CelestialObject>,
-    pub g: f64, // gravitational constant in m^3 kg^-1 s^-2
+    pub units: Units, // units of every stored quantity, G and c follow from them
+    pub solver: Box<dyn ForceSolver>,
+    pub softening: f64, // Plummer softening length ε, bodies may override it with their own
+    pub time: f64, // simulated time since the system was created, in `units`
+    pub collision_policy: CollisionPolicy,
+    pub collisions: Vec<Collision>, // every collision so far, oldest first
+    pub baseline: Option<Diagnostics>, // measured when the first step is taken
+    pub report_every: usize, // print the state every this many steps of `run`, 0 never
+    pub latest: Option<Diagnostics>, // measured every `measure_every` steps, for displays redrawn more often
+    pub measure_every: usize, // steps between measurements of `latest`
+    pub steps: u64, // steps taken since the system was created
+    pub analytic_pairs: Vec<(String, String)>, // isolated pairs moved on exact Kepler orbits
+    pub timesteps: Option<BlockTimesteps>, // per-body block timesteps, None for one global step
+    pub frame: Frame, // frame the TUI and exporters show the bodies in, integration stays inertial
+    pub detector: Option<EventDetector>, // created by the first `watch`
+    pub events: Vec<Event>, // detected and not drained yet, oldest first
+    pub on_event: Option<EventCallback>, // called for every event as it is detected
+    pub recorder: Option<Recorder>, // writes the bodies' time series, in `frame`, as they are stepped
+    pub generation: u64, // bumped whenever bodies are added, removed, merged or split; bump it when editing `bodies`
+    pub evaluated: Option<u64>, // generation the bodies' accelerations were last evaluated for
 }
 
 impl StarSystem {
     pub fn new() -> Self {
         StarSystem {
             bodies: Vec::new(),
-            g: 6.67430e-11, 
+            units: Units::SI,
+            solver: Box::new(BarnesHut::new(0.5)),
+            softening: 0.0,
+            time: 0.0,
+            collision_policy: CollisionPolicy::default(),
+            collisions: Vec::new(),
+            baseline: None,
+            report_every: 0,
+            latest: None,
+            measure_every: MEASURE_EVERY,
+            steps: 0,
+            analytic_pairs: Vec::new(),
+            timesteps: None,
+            frame: Frame::Inertial,
+            detector: None,
+            events: Vec::new(),
+            on_event: None,
+            recorder: None,
+            generation: 0,
+            evaluated: None,
         }
     }
     
+    /// Adds a body given in the system's units.
     pub fn add_body(&mut self, body: CelestialObject) {
         self.bodies.push(body);
+        self.generation += 1;
+    }
+
+    /// Adds a body given in SI, converting it to the system's units.
+    pub fn add_body_si(&mut self, body: CelestialObject) {
+        self.bodies.push(self.units.from_si(&body));
+        self.generation += 1;
+    }
+
+    /// Re-expresses the whole system in `units`.
+    ///
+    /// Bodies, time, softening and past collisions are converted, so the dynamics are unchanged;
+    /// the drift baseline and `latest` are measured again on the next step, as energies change units too.
+    pub fn with_units(mut self, units: Units) -> Self {
+        let from = self.units;
+        for body in &mut self.bodies {
+            *body = from.convert(body, units);
+        }
+        let (length, time) = (from.length / units.length, from.time / units.time);
+        self.time *= time;
+        self.softening *= length;
+        for collision in &mut self.collisions {
+            collision.time *= time;
+            collision.position *= length;
+            collision.relative_speed *= length / time;
+        }
+        self.baseline = None;
+        self.latest = None;
+        self.units = units;
+        self
+    }
+
+    /// Moves the origin to the barycentre and zeroes the total momentum, so the system does not drift away.
+    pub fn centre(&mut self) {
+        frame::centre(&mut self.bodies);
+    }
+
+    /// Copies of the bodies seen in `frame`, None if it names a body that is not in the system.
+    pub fn view(&self, frame: &Frame) -> Option<Vec<CelestialObject>> {
+        frame.transform(&self.bodies)
+    }
+
+    /// Copies of the bodies in the output `frame`, or inertial ones if its body has gone, e.g. merged.
+    pub fn observed(&self) -> Vec<CelestialObject> {
+        self.view(&self.frame).unwrap_or_else(|| self.bodies.clone())
+    }
+
+    /// The bodies converted to SI, for output.
+    pub fn bodies_si(&self) -> Vec<CelestialObject> {
+        self.bodies.iter().map(|body| self.units.to_si(body)).collect()
+    }
+
+    /// Measures `latest` every `steps` steps instead of every `MEASURE_EVERY`.
+    pub fn with_measure_every(mut self, steps: usize) -> Self {
+        self.measure_every = steps.max(1);
+        self
+    }
+
+    /// Sets the Plummer softening length used for every pair without its own.
+    pub fn with_softening(mut self, softening: f64) -> Self {
+        self.softening = softening;
+        self
+    }
+
+    /// Moves the two named bodies on their exact mutual Kepler orbit instead of integrating them.
+    ///
+    /// Meant for pairs far from everything else, such as distant moons: they neither feel nor
+    /// exert forces on the rest of the system any more. Returns false if a name is unknown.
+    pub fn fast_forward_pair(&mut self, a: &str, b: &str) -> bool {
+        let known = |name: &str| self.bodies.iter().any(|body| body.name == name);
+        if !known(a) || !known(b) || a == b {
+            return false;
+        }
+        self.analytic_pairs.push((a.to_string(), b.to_string()));
+        self.generation += 1; // the pair leaves the integrated bodies
+        true
+    }
+
+    /// One step of the N-body integration, with the analytic pairs propagated on their own.
+    fn step_bodies(&mut self, integrator: &dyn Integrator, dt: f64) {
+        let gravity = self.gravity();
+        let changed = self.evaluated != Some(self.generation);
+        self.evaluated = Some(self.generation);
+        let pairs: Vec<(usize, usize)> = self.analytic_pairs.iter()
+            .filter_map(|(a, b)| {
+                let a = self.bodies.iter().position(|body| &body.name == a)?;
+                let b = self.bodies.iter().position(|body| &body.name == b)?;
+                Some((a.min(b), a.max(b)))
+            })
+            .collect();
+        if pairs.is_empty() {
+            advance(&mut self.bodies, self.solver.as_ref(), self.timesteps.as_mut(), integrator, gravity, dt, changed);
+            return;
+        }
+
+        let analytic: Vec<bool> = (0..self.bodies.len())
+            .map(|i| pairs.iter().any(|&(a, b)| a == i || b == i))
+            .collect();
+        let mut numeric: Vec<CelestialObject> = self.bodies.iter()
+            .zip(&analytic)
+            .filter(|(_, &analytic)| !analytic)
+            .map(|(body, _)| body.clone())
+            .collect();
+        advance(&mut numeric, self.solver.as_ref(), self.timesteps.as_mut(), integrator, gravity, dt, changed);
+
+        let mut numeric = numeric.into_iter();
+        for (body, &analytic) in self.bodies.iter_mut().zip(&analytic) {
+            if !analytic {
+                *body = numeric.next().unwrap();
+            }
+        }
+        for (a, b) in pairs {
+            let (left, right) = self.bodies.split_at_mut(b);
+            kepler::propagate_pair(&mut left[a], &mut right[0], dt, gravity.g);
+        }
+    }
+
+    /// Gives every body its own power-of-two fraction of the step, down to dt / 2^`max_level`.
+    ///
+    /// Steps then use kick-drift-kick leapfrog on each body's own timestep instead of the integrator
+    /// passed to `simulate`, and only the bodies due on a substep get their forces recomputed.
+    pub fn with_block_timesteps(mut self, criterion: Criterion, max_level: u32) -> Self {
+        self.timesteps = Some(BlockTimesteps::new(criterion, max_level));
+        self
+    }
+
+    fn body(&self, name: &str) -> Option<&CelestialObject> {
+        self.bodies.iter().find(|body| body.name == name)
+    }
+
+    /// Which body orbits which right now, see `Hierarchy::detect`.
+    pub fn hierarchy(&self) -> Hierarchy {
+        Hierarchy::detect(&self.bodies, self.units.g())
+    }
+
+    /// (body, parent, elements) for every body with a parent in the hierarchy, parents before children.
+    pub fn orbital_elements(&self) -> Vec<(String, String, OrbitalElements)> {
+        let hierarchy = self.hierarchy();
+        let elements = hierarchy.elements(&self.bodies, self.units.g());
+        hierarchy.walk()
+            .into_iter()
+            .filter_map(|(_, i)| {
+                let parent = hierarchy.parent(i)?;
+                Some((self.bodies[i].name.clone(), self.bodies[parent].name.clone(), elements[i]?))
+            })
+            .collect()
+    }
+
+    /// L1..L5 of the named pair right now, None if a name is unknown.
+    pub fn lagrange_points(&self, primary: &str, secondary: &str) -> Option<[LagrangePoint; 5]> {
+        Some(lagrange::lagrange_points(self.body(primary)?, self.body(secondary)?))
+    }
+
+    /// Hill sphere radius of `secondary` around `primary`, None if a name is unknown.
+    pub fn hill_radius(&self, primary: &str, secondary: &str) -> Option<f64> {
+        Some(lagrange::hill_radius(self.body(primary)?, self.body(secondary)?, self.units.g()))
+    }
+
+    /// Sphere of influence of `secondary` around `primary`, None if a name is unknown.
+    pub fn sphere_of_influence(&self, primary: &str, secondary: &str) -> Option<f64> {
+        Some(lagrange::sphere_of_influence(self.body(primary)?, self.body(secondary)?, self.units.g()))
+    }
+
+    /// Adds `per_point` test particles around each of L4 and L5 of the named pair, see `lagrange::trojans`.
+    /// Returns how many were added, 0 if a name is unknown.
+    pub fn add_trojans(&mut self, primary: &str, secondary: &str, per_point: usize, spread: f64) -> usize {
+        let (Some(primary), Some(secondary)) = (self.body(primary), self.body(secondary)) else {
+            return 0;
+        };
+        let trojans = lagrange::trojans(primary, secondary, per_point, spread);
+        let added = trojans.len();
+        self.bodies.extend(trojans);
+        self.generation += 1;
+        added
+    }
+
+    /// Starts detecting `watch` events from the next step on, see `engine::events`.
+    pub fn watch(&mut self, watch: Watch) {
+        self.detector.get_or_insert_with(EventDetector::default).watches.push(watch);
+    }
+
+    /// Calls `callback` for every detected event; the events are still kept for `drain_events`.
+    pub fn on_event(&mut self, callback: impl FnMut(&Event) + 'static) {
+        self.on_event = Some(Box::new(callback));
+    }
+
+    /// Takes every event detected so far, oldest first.
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, Event> {
+        self.events.drain(..)
+    }
+
+    /// Reads the system of a scenario file, see `Scenario` for the format; `Scenario::load` also
+    /// gives the integrator and time step it is meant to be run with.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<StarSystem> {
+        Scenario::load(path).map(|scenario| scenario.system)
+    }
+
+    /// Writes the current state, units and solver as a scenario file, to be run with `integrator` and `dt`.
+    pub fn save(&self, path: impl AsRef<Path>, integrator: &dyn Integrator, dt: f64) -> io::Result<()> {
+        Scenario::save(self, path, integrator, dt)
+    }
+
+    /// Records the bodies' time series with `recorder` from the next step on, see `Recorder`.
+    pub fn with_recorder(mut self, recorder: Recorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// Hands the bodies, in the output frame, to the recorder if it is due; drops it if writing fails.
+    fn record(&mut self) {
+        if !self.recorder.as_mut().is_some_and(Recorder::tick) {
+            return;
+        }
+        let bodies = self.observed();
+        if let Some(Err(error)) = self.recorder.as_mut().map(|recorder| recorder.record(&bodies, self.time, self.units, self.softening)) {
+            eprintln!("Cannot record the trajectory, recording stopped: {error}");
+            self.recorder = None;
+        }
+    }
+
+    /// Replaces the force backend, e.g. `DirectSum` for exact reference runs.
+    pub fn with_solver(mut self, solver: Box<dyn ForceSolver>) -> Self {
+        self.solver = solver;
+        self
     }
 
     pub fn solar() -> Self {
@@ -92,7 +399,7 @@ impl StarSystem {
             Vector2::new(0.0, 0.0),
             Vector2::new(0.0, 0.0),
             Point2::new(0.0, 0.0), // initial prevposition
-        );
+        ).with_radius(6.957e8);
         let mercury = CelestialObject::new(
             "Mercury".to_string(),
             3.302e23,
@@ -100,7 +407,7 @@ impl StarSystem {
             Vector2::new(0.0, 47.36e3),
             Vector2::new(0.0, 0.0),
             Point2::new(57.909e9, 0.0), // initial prevposition
-        );
+        ).with_radius(2.4397e6);
         let venus = CelestialObject::new(
             "Venus".to_string(),
             4.869e24,
@@ -108,7 +415,7 @@ impl StarSystem {
             Vector2::new(0.0, 35.02e3),
             Vector2::new(0.0, 0.0),
             Point2::new(108.208e9, 0.0), // initial prevposition
-        );
+        ).with_radius(6.0518e6);
         let earth = CelestialObject::new(
             "Earth".to_string(),
             5.972e24,
@@ -116,7 +423,7 @@ impl StarSystem {
             Vector2::new(0.0, 29.78e3),
             Vector2::new(0.0, 0.0),
             Point2::new(149.596e9, 0.0), // initial prevposition
-        );
+        ).with_radius(6.371e6);
         let mars = CelestialObject::new(
             "Mars".to_string(),
             6.419e23,
@@ -124,7 +431,7 @@ impl StarSystem {
             Vector2::new(0.0, 24.07e3),
             Vector2::new(0.0, 0.0),
             Point2::new(227.939e9, 0.0), // initial prevposition
-        );
+        ).with_radius(3.3895e6);
         let jupiter = CelestialObject::new(
             "Jupiter".to_string(),
             1.898e27,
@@ -132,7 +439,7 @@ impl StarSystem {
             Vector2::new(0.0, 13.07e3),
             Vector2::new(0.0, 0.0),
             Point2::new(778.299e9, 0.0), // initial prevposition
-        );
+        ).with_radius(6.9911e7);
         let saturn = CelestialObject::new(
             "Saturn".to_string(),
             5.684e26,
@@ -140,7 +447,7 @@ impl StarSystem {
             Vector2::new(0.0, 9.69e3),
             Vector2::new(0.0, 0.0),
             Point2::new(1427.0e9, 0.0), // initial prevposition
-        );
+        ).with_radius(5.8232e7);
         let uranus = CelestialObject::new(
             "Uranus".to_string(),
             8.681e25,
@@ -148,7 +455,7 @@ impl StarSystem {
             Vector2::new(0.0, 6.8e3),
             Vector2::new(0.0, 0.0),
             Point2::new(2870.972e9, 0.0), // initial prevposition
-        );
+        ).with_radius(2.5362e7);
         let neptune = CelestialObject::new(
             "Neptune".to_string(),
             1.024e26,
@@ -156,21 +463,114 @@ impl StarSystem {
             Vector2::new(0.0, 5.43e3),
             Vector2::new(0.0, 0.0),
             Point2::new(4497.072e9, 0.0), // initial prevposition
-        );
+        ).with_radius(2.4622e7);
 
-        StarSystem {
+        let mut system = StarSystem {
             bodies: vec![sun, mercury, venus, earth, mars, jupiter, saturn, uranus, neptune],
-            g: 6.67430e-11, 
+            ..StarSystem::new()
+        };
+        system.centre(); // the planets all start on one side, so the Sun would wander off
+        system
+    }
+}
+
+/// Advances `bodies` by `dt`, on block timesteps if there are any and with `integrator` otherwise.
+/// `changed` tells that the bodies changed since the last step, so their accelerations are stale.
+fn advance(
+    bodies: &mut [CelestialObject],
+    solver: &dyn ForceSolver,
+    timesteps: Option<&mut BlockTimesteps>,
+    integrator: &dyn Integrator,
+    gravity: Gravity,
+    dt: f64,
+    changed: bool,
+) {
+    match timesteps {
+        Some(timesteps) => {
+            if changed {
+                timesteps.reset();
+            }
+            timesteps.step(bodies, dt, &|bodies, active| solver.accelerations_on(bodies, active, gravity));
+        }
+        None => {
+            let field = |bodies: &[CelestialObject]| solver.accelerations(bodies, gravity);
+            if changed {
+                integrator::refresh(bodies, &field);
+            }
+            integrator.step(bodies, dt, &field);
         }
     }
 }
 
+impl Default for StarSystem {
+    fn default() -> Self {
+        StarSystem::new()
+    }
+}
+
 impl Simulatable for StarSystem {
     fn bodies(&self) -> &Vec<CelestialObject> {
         &self.bodies
     }
 
-    fn simulate(&mut self, dt: f64, num_steps: usize) {
-        physics::simulate(&mut self.bodies, dt, num_steps, 0.5);
+    fn time(&self) -> f64 {
+        self.time
+    }
+
+    fn gravity(&self) -> Gravity {
+        self.units.gravity(self.softening)
+    }
+
+    fn units(&self) -> Units {
+        self.units
+    }
+
+    fn baseline(&self) -> Option<&Diagnostics> {
+        self.baseline.as_ref()
+    }
+
+    fn report_every(&self) -> usize {
+        self.report_every
+    }
+
+    fn simulate(&mut self, integrator: &dyn Integrator, dt: f64, num_steps: usize) {
+        if self.baseline.is_none() {
+            self.baseline = Some(self.diagnostics());
+            self.latest = self.baseline;
+        }
+        let g = self.units.g();
+        if let Some(detector) = &mut self.detector {
+            detector.observe(&self.bodies, self.time, g); // records the starting state, bodies may have been edited
+        }
+        if self.recorder.as_ref().is_some_and(|recorder| !recorder.started()) {
+            self.record();
+        }
+        for _ in 0..num_steps {
+            self.step_bodies(integrator, dt);
+            self.time += dt;
+            self.steps += 1;
+
+            if self.bodies.iter().any(|body| body.radius > 0.0) {
+                let quad_tree = QuadTree::from_bodies(&self.bodies, 4);
+                let collisions = collision::resolve(&mut self.bodies, &quad_tree, self.time, &self.collision_policy);
+                if !collisions.is_empty() {
+                    self.generation += 1;
+                }
+                self.collisions.extend(collisions);
+            }
+
+            if let Some(detector) = &mut self.detector {
+                for event in detector.observe(&self.bodies, self.time, g) {
+                    if let Some(callback) = &mut self.on_event {
+                        callback(&event);
+                    }
+                    self.events.push(event);
+                }
+            }
+            if self.steps.is_multiple_of(self.measure_every as u64) {
+                self.latest = Some(self.diagnostics());
+            }
+            self.record();
+        }
     }
 }
\ No newline at end of file
@@ -0,0 +1,153 @@
+use crate::engine::diagnostics::Diagnostics;
+use crate::space::objects::{CelestialObject, Gravity, AU, C, G};
+
+pub const SOLAR_MASS: f64 = 1.989e30; // in kg
+pub const DAY: f64 = 86_400.0; // in s
+
+/// Units of length, time and mass a system is simulated in, each given in SI.
+///
+/// Positions, velocities, masses and time are stored in these units, and G and c are rescaled
+/// to match, so the physics is the same in every unit system. Pick units in which the numbers
+/// stay near 1: a solar system in AU/day/M☉ or a cluster in Hénon units instead of 1e11 m and 1e30 kg.
+/// Bodies are converted at input with `from_si` and at output with `to_si`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Units {
+    pub length: f64, // meters per unit of length
+    pub time: f64, // seconds per unit of time
+    pub mass: f64, // kilograms per unit of mass
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Units::SI
+    }
+}
+
+impl Units {
+    pub const SI: Units = Units { length: 1.0, time: 1.0, mass: 1.0 };
+
+    /// Astronomical units, days and solar masses; G is the square of Gauss' constant, ~2.96e-4.
+    pub const ASTRONOMICAL: Units = Units { length: AU, time: DAY, mass: SOLAR_MASS };
+
+    /// Hénon N-body units for a system of `mass` kg and characteristic size `length` m, in which G = M = 1.
+    pub fn henon(mass: f64, length: f64) -> Self {
+        Units { length, time: (length.powi(3) / (G * mass)).sqrt(), mass }
+    }
+
+    /// Hénon units for a solar mass within an AU, the preset scenario files name "henon": G = 1 and
+    /// a unit of time of a year over 2π.
+    pub fn henon_solar() -> Self {
+        Units::henon(SOLAR_MASS, AU)
+    }
+
+    /// Standard Hénon units of a bound system given in SI: G = M = 1 and total energy -1/4,
+    /// which makes the virial radius 1. None if the system is unbound or massless.
+    pub fn henon_for(bodies: &[CelestialObject]) -> Option<Self> {
+        let mass = bodies.iter().map(|body| body.mass).sum::<f64>();
+        let energy = Diagnostics::measure(bodies, 0.0, Gravity::default()).energy();
+        if mass <= 0.0 || energy >= 0.0 {
+            return None;
+        }
+        Some(Units::henon(mass, G * mass * mass / (-4.0 * energy)))
+    }
+
+    pub fn name(self) -> &'static str {
+        if self == Units::SI {
+            "SI"
+        } else if self == Units::ASTRONOMICAL {
+            "AU/day/Msun"
+        } else if (self.g() - 1.0).abs() < 1e-12 {
+            "Henon"
+        } else {
+            "custom"
+        }
+    }
+
+    /// Gravitational constant in these units.
+    pub fn g(self) -> f64 {
+        G * self.mass * self.time * self.time / self.length.powi(3)
+    }
+
+    /// Speed of light in these units.
+    pub fn c(self) -> f64 {
+        C * self.time / self.length
+    }
+
+    /// Gravity kernel constants for these units and a softening length given in them.
+    pub fn gravity(self, softening: f64) -> Gravity {
+        Gravity { g: self.g(), c: self.c(), softening }
+    }
+
+    /// Meters per second in one unit of velocity.
+    pub fn velocity(self) -> f64 {
+        self.length / self.time
+    }
+
+    /// Meters per second squared in one unit of acceleration.
+    pub fn acceleration(self) -> f64 {
+        self.length / (self.time * self.time)
+    }
+
+    /// Joules in one unit of energy.
+    pub fn energy(self) -> f64 {
+        self.mass * self.velocity() * self.velocity()
+    }
+
+    /// Rescales every dimensional field of `body` by the given factors, SI per unit.
+    fn scale(body: &CelestialObject, length: f64, time: f64, mass: f64) -> CelestialObject {
+        let mut scaled = body.clone();
+        scaled.mass = body.mass * mass;
+        scaled.position = body.position * length;
+        scaled.prevposition = body.prevposition * length;
+        scaled.velocity = body.velocity * (length / time);
+        scaled.acceleration = body.acceleration * (length / (time * time));
+        scaled.radius = body.radius * length;
+        scaled.softening = body.softening.map(|softening| softening * length);
+        scaled
+    }
+
+    /// A body given in these units, expressed in SI.
+    pub fn to_si(self, body: &CelestialObject) -> CelestialObject {
+        Units::scale(body, self.length, self.time, self.mass)
+    }
+
+    /// A body given in SI, expressed in these units.
+    pub fn from_si(self, body: &CelestialObject) -> CelestialObject {
+        Units::scale(body, 1.0 / self.length, 1.0 / self.time, 1.0 / self.mass)
+    }
+
+    /// A body given in these units, expressed in `other`.
+    pub fn convert(self, body: &CelestialObject, other: Units) -> CelestialObject {
+        Units::scale(body, self.length / other.length, self.time / other.time, self.mass / other.mass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::space::system::StarSystem;
+
+    #[test]
+    fn henon_units_of_the_solar_system() {
+        let bodies = StarSystem::solar().bodies;
+        let units = Units::henon_for(&bodies).unwrap();
+        assert!((units.g() - 1.0).abs() < 1e-12, "G = {}", units.g());
+
+        // total mass 1 and energy -1/4 once converted
+        let converted: Vec<CelestialObject> = bodies.iter().map(|body| units.from_si(body)).collect();
+        assert!((converted.iter().map(|body| body.mass).sum::<f64>() - 1.0).abs() < 1e-12);
+        let energy = Diagnostics::measure(&converted, 0.0, units.gravity(0.0)).energy();
+        assert!((energy + 0.25).abs() < 1e-9, "E = {energy}");
+
+        for (body, converted) in bodies.iter().zip(&converted) {
+            let back = units.to_si(converted);
+            assert!((back.position - body.position).norm() <= 1e-12 * body.position.coords.norm().max(1.0), "{}", body.name);
+            assert!((back.velocity - body.velocity).norm() <= 1e-12 * body.velocity.norm().max(1.0), "{}", body.name);
+            assert!((back.mass / body.mass - 1.0).abs() < 1e-12, "{}", body.name);
+            // converting between two non-SI systems goes the same way
+            let astronomical = units.convert(converted, Units::ASTRONOMICAL);
+            let direct = Units::ASTRONOMICAL.from_si(body);
+            assert!((astronomical.position - direct.position).norm() <= 1e-12 * direct.position.coords.norm().max(1.0), "{}", body.name);
+        }
+    }
+}
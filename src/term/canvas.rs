@@ -0,0 +1,64 @@
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::canvas::{Circle, Context, Points};
+
+use crate::space::lagrange::LagrangePoint;
+use crate::space::objects::{BodyKind, CelestialObject, Position};
+
+/// Color used for a body on the canvas: its own colour if it has one, else that of its kind.
+pub fn body_color(body: &CelestialObject) -> Color {
+    if let Some([r, g, b]) = body.colour {
+        return Color::Rgb(r, g, b);
+    }
+    match body.kind {
+        BodyKind::Regular => Color::White,
+        BodyKind::BlackHole => Color::Magenta,
+        BodyKind::TestParticle => Color::DarkGray,
+    }
+}
+
+/// Draws `body` in world coordinates. `resolution` is the width of one Braille dot in meters,
+/// so outlines smaller than a dot are still drawn a few dots wide.
+pub fn draw_body(ctx: &mut Context, body: &CelestialObject, resolution: f64) {
+    let (x, y) = (body.position.x, body.position.y);
+    match body.kind {
+        BodyKind::Regular | BodyKind::TestParticle => {
+            if body.radius > resolution {
+                ctx.draw(&Circle { x, y, radius: body.radius, color: body_color(body) });
+            }
+            ctx.draw(&Points { coords: &[(x, y)], color: body_color(body) });
+        }
+        BodyKind::BlackHole => {
+            // the horizon as a ring, with an empty (dark) center
+            let horizon = body.radius.max(2.0 * resolution);
+            ctx.draw(&Circle { x, y, radius: horizon, color: body_color(body) });
+            ctx.draw(&Circle { x, y, radius: horizon * 1.5, color: Color::DarkGray });
+        }
+    }
+}
+
+/// Prints the body's name just right of it.
+pub fn draw_label(ctx: &mut Context, body: &CelestialObject, resolution: f64) {
+    let style = Style::default().fg(body_color(body));
+    let name = match body.kind {
+        BodyKind::Regular | BodyKind::TestParticle => Span::styled(body.name.clone(), style),
+        BodyKind::BlackHole => Span::styled(format!("{} ⦿", body.name), style),
+    };
+    ctx.print(body.position.x + 3.0 * resolution, body.position.y, Line::from(name));
+}
+
+/// Overlays L1..L5 as small crosses with their names.
+pub fn draw_lagrange_points(ctx: &mut Context, points: &[LagrangePoint; 5], resolution: f64) {
+    let style = Style::default().fg(Color::Cyan);
+    for (i, point) in points.iter().enumerate() {
+        let (x, y) = (point.position.x, point.position.y);
+        let arm = 2.0 * resolution;
+        ctx.draw(&Points { coords: &[(x, y), (x - arm, y), (x + arm, y), (x, y - arm), (x, y + arm)], color: Color::Cyan });
+        ctx.print(x + 3.0 * resolution, y, Line::from(Span::styled(format!("L{}", i + 1), style)));
+    }
+}
+
+/// Outlines a sphere such as a Hill sphere or sphere of influence around `center`.
+pub fn draw_sphere(ctx: &mut Context, center: Position, radius: f64, color: Color) {
+    ctx.draw(&Circle { x: center.x, y: center.y, radius, color });
+}
@@ -1,2 +1,5 @@
 pub mod terminal;
-pub mod prelude;
\ No newline at end of file
+pub mod prelude;
+pub mod canvas;
+pub mod panels;
+pub mod replay;
\ No newline at end of file
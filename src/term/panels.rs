@@ -0,0 +1,153 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+use crate::engine::diagnostics::Diagnostics;
+use crate::engine::events::{Event, EventKind};
+use crate::gen::replay::Replay;
+use crate::space::hierarchy::{Hierarchy, Role};
+use crate::space::objects::CelestialObject;
+use crate::space::orbits::OrbitalElements;
+
+/// Text lines with the conserved quantities and, given a baseline, their drift since t=0.
+pub fn diagnostics_lines(diagnostics: &Diagnostics, baseline: Option<&Diagnostics>) -> Vec<Line<'static>> {
+    let label = Style::default().fg(Color::DarkGray);
+    let row = |name: &str, value: String| Line::from(vec![
+        Span::styled(format!("{name:<8}"), label),
+        Span::raw(value),
+    ]);
+
+    let mut lines = vec![
+        row("t", format!("{:.3e} s", diagnostics.time)),
+        row("E", format!("{:.6e}", diagnostics.energy())),
+        row("K / U", format!("{:.3e} / {:.3e}", diagnostics.kinetic, diagnostics.potential)),
+        row("|P|", format!("{:.3e}", diagnostics.momentum.norm())),
+        row("L", format!("{:.3e}", diagnostics.angular_momentum)),
+        row("2K/|U|", format!("{:.4}", diagnostics.virial_ratio)),
+    ];
+    if let Some(baseline) = baseline {
+        let drift = diagnostics.drift(baseline);
+        let style = if drift.energy.abs() > 1e-3 { Style::default().fg(Color::Red) } else { Style::default().fg(Color::Green) };
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:<8}", "dE/E"), label),
+            Span::styled(format!("{:+.2e}", drift.energy), style),
+        ]));
+        lines.push(row("dP", format!("{:+.2e}", drift.momentum)));
+        lines.push(row("dL/L", format!("{:+.2e}", drift.angular_momentum)));
+    }
+    lines
+}
+
+/// The body list as an indented tree, one line per body with its role; `selected` is highlighted.
+pub fn body_tree_lines(bodies: &[CelestialObject], hierarchy: &Hierarchy, selected: Option<usize>) -> Vec<Line<'static>> {
+    let role_style = |role: Role| Style::default().fg(match role {
+        Role::Star => Color::Yellow,
+        Role::Planet => Color::White,
+        Role::Moon => Color::Gray,
+        Role::BinaryMember => Color::LightYellow,
+        Role::Unbound => Color::Red,
+    });
+    hierarchy.walk()
+        .into_iter()
+        .filter(|&(_, i)| !bodies[i].is_test_particle()) // thousands of them would drown the list
+        .map(|(depth, i)| {
+            let role = hierarchy.role(i).unwrap_or(Role::Unbound);
+            let name = if Some(i) == selected {
+                Span::styled(bodies[i].name.clone(), Style::default().fg(Color::Black).bg(Color::White))
+            } else {
+                Span::raw(bodies[i].name.clone())
+            };
+            Line::from(vec![
+                Span::raw(format!("{}{}", "  ".repeat(depth), if depth > 0 { "└ " } else { "" })),
+                name,
+                Span::styled(format!(" {}", role.label()), role_style(role)),
+            ])
+        })
+        .collect()
+}
+
+/// Orbital elements of a body around its parent in the hierarchy, angles in degrees.
+pub fn elements_lines(parent: &str, elements: &OrbitalElements) -> Vec<Line<'static>> {
+    let label = Style::default().fg(Color::DarkGray);
+    let row = |name: &str, value: String| Line::from(vec![
+        Span::styled(format!("{name:<8}"), label),
+        Span::raw(value),
+    ]);
+    vec![
+        row("around", parent.to_string()),
+        row("a", format!("{:.4e}", elements.semi_major_axis)),
+        row("e", format!("{:.5}", elements.eccentricity)),
+        row("i", format!("{:.1}°", elements.inclination.to_degrees())),
+        row("ω", format!("{:.2}°", elements.argument_of_periapsis.to_degrees())),
+        row("ν", format!("{:.2}°", elements.true_anomaly.to_degrees())),
+    ]
+}
+
+/// Radii of the Hill sphere and sphere of influence, in the colours they are outlined in.
+pub fn sphere_lines(hill: f64, influence: f64) -> Vec<Line<'static>> {
+    let label = Style::default().fg(Color::DarkGray);
+    vec![
+        Line::from(vec![Span::styled(format!("{:<8}", "r_Hill"), label), Span::styled(format!("{hill:.4e}"), Style::default().fg(Color::Cyan))]),
+        Line::from(vec![Span::styled(format!("{:<8}", "r_SOI"), label), Span::styled(format!("{influence:.4e}"), Style::default().fg(Color::Blue))]),
+    ]
+}
+
+/// The last `count` of `events`, oldest first, for the log panel, newest at the bottom.
+pub fn event_lines<'a>(events: impl DoubleEndedIterator<Item = &'a Event>, count: usize) -> Vec<Line<'static>> {
+    let mut lines: Vec<Line<'static>> = events
+        .rev()
+        .take(count)
+        .map(|event| {
+            let color = match event.kind {
+                EventKind::Periapsis { .. } | EventKind::Apoapsis { .. } => Color::Gray,
+                EventKind::CloseApproach { .. } => Color::Yellow,
+                EventKind::Conjunction { .. } | EventKind::Opposition { .. } => Color::Cyan,
+                EventKind::Transit { .. } => Color::LightBlue,
+                EventKind::Escape { .. } => Color::Red,
+            };
+            Line::from(Span::styled(event.to_string(), Style::default().fg(color)))
+        })
+        .collect();
+    lines.reverse();
+    lines
+}
+
+/// Playback state of a replay: play or pause with direction and speed, the frame, the recorded
+/// time and a progress bar `width` cells wide.
+pub fn replay_lines(replay: &Replay, time: f64, width: usize) -> Vec<Line<'static>> {
+    let label = Style::default().fg(Color::DarkGray);
+    let state = match (replay.playing, replay.speed < 0.0) {
+        (false, _) => Span::styled("❚❚ paused", Style::default().fg(Color::Yellow)),
+        (true, false) => Span::styled("▶ playing", Style::default().fg(Color::Green)),
+        (true, true) => Span::styled("◀ reversing", Style::default().fg(Color::Cyan)),
+    };
+    let last = replay.frames().saturating_sub(1).max(1);
+    let filled = (replay.frame() as usize * width) / last as usize;
+    let (start, end) = replay.time_range();
+    vec![
+        Line::from(vec![
+            state,
+            Span::styled(format!("  {} frames/s", replay.speed.abs()), label),
+        ]),
+        Line::from(vec![
+            Span::styled(format!("{:<8}", "frame"), label),
+            Span::raw(format!("{} / {}", replay.frame(), replay.frames())),
+        ]),
+        Line::from(vec![
+            Span::styled(format!("{:<8}", "t"), label),
+            Span::raw(format!("{time:.3e} of {start:.3e}..{end:.3e}")),
+        ]),
+        Line::from(vec![
+            Span::styled("█".repeat(filled.min(width)), Style::default().fg(Color::Green)),
+            Span::styled("░".repeat(width - filled.min(width)), label),
+        ]),
+    ]
+}
+
+/// Keys and what they do, for the bottom line of the screen.
+pub fn shortcuts_line(pairs: &[(&str, &'static str)]) -> Line<'static> {
+    let key = Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD);
+    let label = Style::default().fg(Color::DarkGray);
+    Line::from(pairs.iter()
+        .flat_map(|&(name, action)| [Span::styled(format!(" {name} "), key), Span::styled(action, label)])
+        .collect::<Vec<_>>())
+}
@@ -0,0 +1,37 @@
+use std::io;
+
+use crossterm::event::KeyCode;
+use ratatui::text::Line;
+
+use crate::gen::replay::Replay;
+use crate::term::panels;
+
+/// Applies a playback key to `replay` and tells whether it was one:
+/// space plays or pauses, ←/→ step a frame, PgUp/PgDn jump a twentieth of the recorded time,
+/// Home/End go to either end, +/- double or halve the speed and r reverses.
+/// Fails if the frame to jump to cannot be found in the recording.
+pub fn apply_key(replay: &mut Replay, key: KeyCode) -> io::Result<bool> {
+    let (start, end) = replay.time_range();
+    let jump = (end - start) / 20.0;
+    match key {
+        KeyCode::Char(' ') => replay.toggle(),
+        KeyCode::Left => replay.step(-1),
+        KeyCode::Right => replay.step(1),
+        KeyCode::PageUp | KeyCode::PageDown => {
+            let now = replay.snapshot()?.time;
+            replay.seek_time(if key == KeyCode::PageUp { now - jump } else { now + jump })?;
+        }
+        KeyCode::Home => replay.seek(0),
+        KeyCode::End => replay.seek(replay.frames() as i64 - 1),
+        KeyCode::Char('+') | KeyCode::Char('=') => replay.scale_speed(2.0),
+        KeyCode::Char('-') => replay.scale_speed(0.5),
+        KeyCode::Char('r') => replay.reverse(),
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// The playback keys, for the bottom line of the screen.
+pub fn shortcuts_line() -> Line<'static> {
+    panels::shortcuts_line(&[("SPACE", "play/pause"), ("←/→", "step"), ("PGUP/PGDN", "seek"), ("HOME/END", "ends"), ("+/-", "speed"), ("R", "reverse")])
+}
@@ -1,206 +1,374 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use ratatui::symbols::Marker;
+use ratatui::widgets::canvas::Canvas;
+
+use crate::engine::events;
+use crate::engine::integrator::Integrator;
+use crate::gen::replay::Replay;
+use crate::space::frame;
+use crate::space::hierarchy::Hierarchy;
+use crate::space::lagrange::{self, LagrangePoint};
+use crate::space::objects::{CelestialObject, Position};
+use crate::space::system::{Simulatable, StarSystem};
+use crate::space::units::Units;
 use crate::term::prelude::*;
+use crate::term::{canvas, panels, replay};
 
-pub struct App {
-    sender: mpsc::Sender<AppEvent>,
-    is_running: bool,
-    last_tick: Duration,
-    aux_buffer: Rc<RefCell<Buffer>>,
-    inspected_effect: u8,
-    screen_area: Rect
-}
+pub type Terminal = ratatui::Terminal<CrosstermBackend<io::Stdout>>;
+
+/// Width of the side panel with the diagnostics or playback state and the body tree.
+const SIDE_PANEL: u16 = 38;
+
+/// Ticks between detections of the hierarchy, which changes far slower than the view is redrawn.
+const HIERARCHY_EVERY: usize = 30;
 
-#[derive(Default)]
-pub struct Fx {
-    pub post_process: Option<Effect>,
+/// Most recent events kept for the log at the bottom of the side panel.
+const EVENT_LOG: usize = 5;
+
+/// The terminal, in raw mode on the alternate screen, and the events coming from it.
+pub struct TerminalInterface {
+    pub terminal: Terminal,
+    events: EventHandler,
 }
 
-impl Fx {
-    pub fn push(&mut self, effect: Effect) {
-        self.post_process = Some(effect);
-    }
+/// What the view shows.
+pub enum Source<'a> {
+    /// A system stepped `steps_per_tick` times by `integrator` on every tick, unless paused.
+    Live { system: &'a mut StarSystem, integrator: &'a dyn Integrator, dt: f64, steps_per_tick: usize, paused: bool },
+    /// A recording played back, see `gen::replay`.
+    Replay(Replay),
+}
 
-    pub fn process_active_fx(
-        &mut self,
-        duration: Duration,
-        buffer: &mut Buffer,
-        area: Rect
-    ) {
-        self.post_process.iter_mut().for_each(|effect| { effect.process(duration, buffer, area); });
-        if self.post_process.iter().all(Effect::done) {
-            self.post_process = None;
-        }
-    }
+pub struct App<'a> {
+    source: Source<'a>,
+    is_running: bool,
+    last_tick: Instant,
+    bodies: Vec<CelestialObject>, // as drawn, in `frame`
+    massive: Vec<CelestialObject>, // all but test particles as stored, for the body tree and orbital elements
+    hierarchy: Hierarchy, // of `massive`, detected again every `HIERARCHY_EVERY` ticks or when bodies come or go
+    hierarchy_age: usize, // ticks since `hierarchy` was detected
+    frame: frame::Frame, // starts as the system's output frame, or as recorded for a replay
+    time: f64,
+    units: Units,
+    zoom: f64, // 1 fits every body in the view
+    follow: Option<String>, // body the view is centred on, None to centre on all of them
+    labels: bool,
+    events: VecDeque<events::Event>, // the last `EVENT_LOG` events drained from a live system, oldest first
+    lagrange: bool, // overlay the Lagrange points and spheres of the followed body
+    error: Option<String>, // why the replay stopped, shown instead of the shortcuts
 }
 
-impl App {
-    pub fn new(
-        sender: mpsc::Sender<AppEvent>,
-        aux_buffer_area: Rect,
-    ) -> Self {
-        Self {
-            sender,
-            is_running: true,
-            last_tick: Duration::ZERO,
-            aux_buffer: Rc::new(RefCell::new(Buffer::empty(aux_buffer_area))),
-            screen_area: Rect::default(),
-            inspected_effect: 0,
-        }
+impl TerminalInterface {
+    pub fn new() -> io::Result<Self> {
+        Ok(TerminalInterface { terminal: setup_terminal()?, events: EventHandler::new(Duration::from_millis(33)) })
     }
 
-    pub fn inspected_effect(&self, areas: EffectTimelineRects) -> Effect {
-        effect_in(self.inspected_effect_no, areas)
+    /// Shows `system` on a canvas and steps it by `dt` as the view ticks, until Esc or q.
+    pub fn run(&mut self, system: &mut StarSystem, integrator: &dyn Integrator, dt: f64) -> io::Result<()> {
+        self.run_app(App::new(Source::Live { system, integrator, dt, steps_per_tick: 1, paused: false }))
     }
 
-    pub fn effect_timeline(&self, areas: EffectTimelineRects) -> EffectTimeline {
-        let idx = self.inspected_effect_no;
-        let area = self.aux_buffer.borrow().area;
-        let fx = transition_fx(area, self.sender.clone(), effect_in(idx, areas));
-
-        EffectTimeline::builder()
-            .effect(&fx)
-            .build()
+    /// Plays `replay` back on the canvas; no physics runs.
+    pub fn replay(&mut self, replay: Replay) -> io::Result<()> {
+        self.run_app(App::new(Source::Replay(replay)))
     }
 
-    pub fn inspected_transition_effect(&self) -> Effect {
-        let area = self.aux_buffer.borrow().area;
-        let layout = self.effect_timeline(baseline_rects()).layout(area);
-        transition_fx(area,  self.sender.clone(), self.inspected_effect(layout))
+    fn run_app(&mut self, mut app: App) -> io::Result<()> {
+        while app.is_running {
+            self.events.receive_events(|e| app.apply_event(e));
+            self.terminal.draw(|f| ui(f, &app))?;
+        }
+        Ok(())
     }
 
-    pub fn refresh_aux_buffer(&self) {
-        let effect = self.inspected_transition_effect();
-
-        let mut buf = self.aux_buffer.borrow_mut();
-        Clear.render(buf.area, &mut buf);
+    /// Leaves raw mode and the alternate screen.
+    pub fn restore(&mut self) -> io::Result<()> {
+        disable_raw_mode()?;
+        execute!(self.terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+        self.terminal.show_cursor()
+    }
+}
 
-        Block::new()
-            .style(Style::default().bg(Color::Black))
-            .render(buf.area, &mut buf);
+impl<'a> App<'a> {
+    pub fn new(source: Source<'a>) -> Self {
+        let frame = match &source {
+            Source::Live { system, .. } => system.frame.clone(),
+            Source::Replay(_) => frame::Frame::Inertial,
+        };
+        let mut app = App {
+            source,
+            is_running: true,
+            last_tick: Instant::now(),
+            bodies: Vec::new(),
+            massive: Vec::new(),
+            hierarchy: Hierarchy::default(),
+            hierarchy_age: 0,
+            frame,
+            time: 0.0,
+            units: Units::SI,
+            zoom: 1.0,
+            follow: None,
+            labels: true,
+            events: VecDeque::with_capacity(EVENT_LOG),
+            lagrange: false,
+            error: None,
+        };
+        app.refresh();
+        app
+    }
 
-        EffectTimeline::builder()
-            .effect(&effect)
-            .build()
-            .render(buf.area, &mut buf);
+    /// Takes the bodies to draw from the source, seen in `frame` unless a body it needs has gone.
+    fn refresh(&mut self) {
+        let seen = |frame: &frame::Frame, bodies: &[CelestialObject]| frame.transform(bodies).unwrap_or_else(|| bodies.to_vec());
+        let massive = |bodies: &[CelestialObject]| bodies.iter().filter(|body| !body.is_test_particle()).cloned().collect();
+        match &mut self.source {
+            Source::Live { system, .. } => {
+                self.bodies = seen(&self.frame, &system.bodies);
+                self.massive = massive(&system.bodies);
+                for event in system.drain_events() {
+                    if self.events.len() == EVENT_LOG {
+                        self.events.pop_front();
+                    }
+                    self.events.push_back(event);
+                }
+                self.time = system.time;
+                self.units = system.units;
+            }
+            Source::Replay(replay) => {
+                let elapsed = self.last_tick.elapsed();
+                match replay.advance(elapsed) {
+                    Ok(snapshot) => {
+                        self.bodies = seen(&self.frame, &snapshot.bodies);
+                        self.massive = massive(&snapshot.bodies);
+                        self.time = snapshot.time;
+                        self.units = snapshot.units;
+                    }
+                    Err(error) => {
+                        replay.playing = false;
+                        self.error = Some(format!("Cannot read frame {}: {error}", replay.frame()));
+                    }
+                }
+            }
+        }
+        self.hierarchy_age += 1;
+        if self.hierarchy_age >= HIERARCHY_EVERY || self.hierarchy.nodes.len() != self.massive.len() {
+            self.hierarchy = Hierarchy::detect(&self.massive, self.units.g());
+            self.hierarchy_age = 0;
+        }
+        self.last_tick = Instant::now();
     }
 
-    pub fn apply_event(&mut self, effects: &mut Effects, e: AppEvent) {
+    pub fn apply_event(&mut self, e: AppEvent) {
         match e {
-            AppEvent::Tick => (),
-            AppEvent::KeyPressed(key) => match key {
-                KeyCode::Esc => self.is_running = false,
-                KeyCode::Char(' ') => {
-                    // sends RefreshAufBuffer after transitioning out
-                    effects.push(self.inspected_transition_effect())
+            AppEvent::Tick => {
+                if let Source::Live { system, integrator, dt, steps_per_tick, paused: false } = &mut self.source {
+                    system.simulate(*integrator, *dt, *steps_per_tick);
                 }
-                KeyCode::Enter => {
-                    self.inspected_effect_no = (self.inspected_effect_no + 1) % 3;
-                    // sends RefreshAufBuffer after transitioning out
-                    effects.push(self.inspected_transition_effect())
-                },
+                self.refresh();
+            }
+            AppEvent::KeyPressed(key) => self.apply_key(key),
+        }
+    }
+
+    fn apply_key(&mut self, key: KeyCode) {
+        match &mut self.source {
+            Source::Live { system, integrator, dt, steps_per_tick, paused } => match key {
+                KeyCode::Char(' ') => *paused = !*paused,
+                KeyCode::Char('.') if *paused => system.simulate(*integrator, *dt, 1),
+                KeyCode::Char('+') | KeyCode::Char('=') => *steps_per_tick = (*steps_per_tick * 2).min(1 << 16),
+                KeyCode::Char('-') => *steps_per_tick = (*steps_per_tick / 2).max(1),
                 _ => (),
             },
-            AppEvent::RefreshAufBuffer => {
-                self.refresh_aux_buffer();
+            Source::Replay(replay) => match replay::apply_key(replay, key) {
+                Ok(true) => self.error = None,
+                Ok(false) => (),
+                Err(error) => self.error = Some(format!("Cannot seek: {error}")),
             },
-            AppEvent::Resize(r) => self.screen_area = r
         }
+        match key {
+            KeyCode::Esc | KeyCode::Char('q') => self.is_running = false,
+            KeyCode::Char('z') => self.zoom *= 1.5,
+            KeyCode::Char('x') => self.zoom /= 1.5,
+            KeyCode::Char('f') => {
+                self.zoom = 1.0;
+                self.follow = None;
+            }
+            KeyCode::Tab => self.follow = self.next_followed(1),
+            KeyCode::BackTab => self.follow = self.next_followed(-1),
+            KeyCode::Char('l') => self.labels = !self.labels,
+            KeyCode::Char('o') => self.frame = self.next_frame(),
+            KeyCode::Char('g') => self.lagrange = !self.lagrange,
+            _ => (),
+        }
+        self.refresh();
     }
-}
-
-fn main() -> Result<()> {
-    let mut terminal = setup_terminal()?;
-
-    // event handler
-    let event_handler = EventHandler::new(Duration::from_millis(33));
-    let sender = event_handler.sender();
-
-    // create app and run it
-    let app = App::new(sender, Rect::new(0, 0, 100, 40));
-    let res = run_app(&mut terminal, app, event_handler);
 
-    // restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-
-    if let Err(err) = res {
-        println!("{err:?}");
+    /// Bodies other than test particles, heaviest first.
+    fn heaviest(&self) -> Vec<&CelestialObject> {
+        let mut bodies: Vec<&CelestialObject> = self.bodies.iter().filter(|body| !body.is_test_particle()).collect();
+        bodies.sort_by(|a, b| b.mass.total_cmp(&a.mass));
+        bodies
     }
 
-    Ok(())
-}
-
-pub type AuxBuffer = Rc<RefCell<Buffer>>;
+    /// The body after (or before) the followed one, heaviest first, wrapping around to following none.
+    fn next_followed(&self, direction: isize) -> Option<String> {
+        let bodies = self.heaviest();
+        let at = self.follow.as_ref()
+            .and_then(|name| bodies.iter().position(|body| &body.name == name))
+            .map_or(bodies.len() as isize, |at| at as isize);
+        let next = (at + direction).rem_euclid(bodies.len() as isize + 1) as usize;
+        bodies.get(next).map(|body| body.name.clone())
+    }
 
-fn run_app(
-    terminal: &mut Terminal,
-    mut app: App,
-    event_handler: EventHandler,
-) -> io::Result<()> {
-    let mut last_frame_instant = std::time::Instant::now();
+    /// The frame after the current one, in the cycle inertial, barycentric, centred on the followed
+    /// (or heaviest) body, co-rotating with the heaviest and the followed (or second heaviest) body.
+    fn next_frame(&self) -> frame::Frame {
+        let bodies = self.heaviest();
+        let primary = bodies.first().map(|body| body.name.clone());
+        let secondary = self.follow.clone()
+            .filter(|name| Some(name) != primary.as_ref())
+            .or_else(|| bodies.get(1).map(|body| body.name.clone()));
+        match (&self.frame, primary, secondary) {
+            (frame::Frame::Inertial, _, _) => frame::Frame::Barycentric,
+            (frame::Frame::Barycentric, Some(primary), _) => frame::Frame::Body(self.follow.clone().unwrap_or(primary)),
+            (frame::Frame::Body(_), Some(primary), Some(secondary)) => frame::Frame::Corotating(primary, secondary),
+            _ => frame::Frame::Inertial,
+        }
+    }
 
-    let mut effects = Effects::default();
-    effects.push(app.inspected_effect(baseline_rects()));
-    app.refresh_aux_buffer();
+    /// L1..L5 of the followed body and its parent, as drawn, with the body's drawn position, Hill
+    /// radius and sphere of influence. None unless the overlay is on and the followed body has a parent.
+    fn overlay(&self) -> Option<([LagrangePoint; 5], Position, f64, f64)> {
+        let name = self.follow.as_ref().filter(|_| self.lagrange)?;
+        let i = self.massive.iter().position(|body| &body.name == name)?;
+        let (primary, secondary) = (&self.massive[self.hierarchy.parent(i)?], &self.massive[i]);
+        let points = lagrange::lagrange_points(primary, secondary).map(|point| {
+            let (position, velocity) = self.frame
+                .to_frame(&self.massive, point.position, point.velocity)
+                .unwrap_or((point.position, point.velocity));
+            LagrangePoint { position, velocity }
+        });
+        let centre = self.bodies.iter().find(|body| &body.name == name)?.position;
+        let g = self.units.g();
+        Some((points, centre, lagrange::hill_radius(primary, secondary, g), lagrange::sphere_of_influence(primary, secondary, g)))
+    }
 
-    while app.is_running {
-        event_handler.receive_events(|e| app.apply_event(&mut effects, e));
+    /// Bounds of the canvas in world coordinates for a view `area` cells large, and the width of one
+    /// Braille dot. Everything but test particles fits in at zoom 1, with the same scale on both
+    /// axes given that a cell is about twice as tall as it is wide.
+    fn bounds(&self, area: Rect) -> ([f64; 2], [f64; 2], f64) {
+        let massive: Vec<&CelestialObject> = self.bodies.iter().filter(|body| !body.is_test_particle()).collect();
+        let shown = if massive.is_empty() { self.bodies.iter().collect() } else { massive };
+        let followed = self.follow.as_ref().and_then(|name| shown.iter().find(|body| &body.name == name));
+
+        let (mut low, mut high) = ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]);
+        for body in &shown {
+            low = [low[0].min(body.position.x - body.radius), low[1].min(body.position.y - body.radius)];
+            high = [high[0].max(body.position.x + body.radius), high[1].max(body.position.y + body.radius)];
+        }
+        if shown.is_empty() {
+            (low, high) = ([-1.0; 2], [1.0; 2]);
+        }
+        let (centre, half) = match followed {
+            Some(body) => {
+                let (x, y) = (body.position.x, body.position.y);
+                ([x, y], [(high[0] - x).max(x - low[0]), (high[1] - y).max(y - low[1])])
+            }
+            None => ([(low[0] + high[0]) / 2.0, (low[1] + high[1]) / 2.0], [(high[0] - low[0]) / 2.0, (high[1] - low[1]) / 2.0]),
+        };
 
-        app.last_tick = last_frame_instant.elapsed();
-        last_frame_instant = std::time::Instant::now();
-        terminal.draw(|f| {
-            app.screen_area = f.area();
-            ui(f, &app, &mut effects)
-        })?;
+        let (width, height) = (area.width.max(1) as f64, area.height.max(1) as f64);
+        let aspect = width / (2.0 * height); // of the area, in world units per world unit
+        let half_y = half[1].max(half[0] / aspect).max(f64::MIN_POSITIVE) * 1.1 / self.zoom;
+        let half_x = half_y * aspect;
+        ([centre[0] - half_x, centre[0] + half_x], [centre[1] - half_y, centre[1] + half_y], half_x / width)
     }
-
-    Ok(())
 }
 
-fn  ui(
-    f: &mut Frame,
-    app: &App,
-    effects: &mut Effects
-) {
+fn ui(f: &mut Frame, app: &App) {
     let rect = f.area();
     if rect.area() == 0 { return; }
 
-    let buf: &mut Buffer = f.buffer_mut();
-    Clear.render(rect, buf);
-
-    let shortcut_key_style = Style::default()
-        .fg(Color::DarkGray)
-        .add_modifier(Modifier::BOLD);
-    let shortcut_label_style = Style::default()
-        .fg(Color::DarkGray);
-
-    app.aux_buffer.render_buffer(Offset::default(), buf);
-    effects.process_active_fx(app.last_tick, buf, rect);
-
-    let shortcuts = Line::from(vec![
-        Span::from("ENTER ").style(shortcut_key_style),
-        Span::from("next transition ").style(shortcut_label_style),
-        Span::from(" SPACE ").style(shortcut_key_style),
-        Span::from("replay transition ").style(shortcut_label_style),
-        Span::from(" ESC ").style(shortcut_key_style),
-        Span::from("quit").style(shortcut_label_style),
-    ]);
-
-    let centered = Rect {
-        x: rect.x + (rect.width - shortcuts.width() as u16) / 2,
-        y: rect.y + rect.height - 1,
-        width: shortcuts.width() as u16,
-        height: 1,
+    let [main, bottom] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(rect);
+    let [view, side] = Layout::horizontal([Constraint::Min(0), Constraint::Length(SIDE_PANEL)]).areas(main);
+
+    let mut title = format!(" t = {:.4e} · {} units · {} ", app.time, app.units.name(), app.frame.label());
+    if let Some(name) = &app.follow {
+        title += &format!("· following {name} ");
+    }
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let massive = &app.massive;
+    let selected = app.follow.as_ref().and_then(|name| massive.iter().position(|body| &body.name == name));
+    let hierarchy = &app.hierarchy;
+    let overlay = app.overlay();
+    let (x_bounds, y_bounds, resolution) = app.bounds(block.inner(view));
+    let canvas = Canvas::default()
+        .block(block)
+        .marker(Marker::Braille)
+        .x_bounds(x_bounds)
+        .y_bounds(y_bounds)
+        .paint(|ctx| {
+            for body in &app.bodies {
+                canvas::draw_body(ctx, body, resolution);
+            }
+            if let Some((points, centre, hill, influence)) = &overlay {
+                canvas::draw_sphere(ctx, *centre, *hill, Color::Cyan);
+                canvas::draw_sphere(ctx, *centre, *influence, Color::Blue);
+                ctx.layer();
+                canvas::draw_lagrange_points(ctx, points, resolution);
+            }
+            if app.labels {
+                ctx.layer();
+                for body in app.bodies.iter().filter(|body| !body.is_test_particle()) {
+                    canvas::draw_label(ctx, body, resolution);
+                }
+            }
+        });
+    f.render_widget(canvas, view);
+
+    let mut lines = match &app.source {
+        Source::Live { system, steps_per_tick, paused, .. } => {
+            // measured every `measure_every` steps rather than on every redraw
+            let mut lines = match &system.latest {
+                Some(latest) => panels::diagnostics_lines(latest, system.baseline.as_ref()),
+                None => Vec::new(),
+            };
+            let state = if *paused { "paused".to_string() } else { format!("{steps_per_tick} steps per tick") };
+            lines.insert(0, Line::from(Span::styled(state, Style::default().fg(Color::Yellow))));
+            lines
+        }
+        Source::Replay(replay) => panels::replay_lines(replay, app.time, SIDE_PANEL as usize - 2),
     };
-    shortcuts.render(centered, buf);
-}
+    lines.push(Line::from(""));
+    lines.extend(panels::body_tree_lines(massive, hierarchy, selected));
+    if let Some(i) = selected {
+        if let Some(parent) = hierarchy.parent(i) {
+            lines.push(Line::from(""));
+            lines.extend(panels::elements_lines(&massive[parent].name, &massive[i].elements_around(&massive[parent], app.units.g())));
+        }
+    }
+    if let Some((_, _, hill, influence)) = overlay {
+        lines.extend(panels::sphere_lines(hill, influence));
+    }
+    if !app.events.is_empty() {
+        lines.push(Line::from(""));
+        lines.extend(panels::event_lines(app.events.iter(), EVENT_LOG));
+    }
+    f.render_widget(Paragraph::new(lines).block(Block::default().borders(Borders::ALL)), side);
 
+    let shortcuts = match (&app.error, &app.source) {
+        (Some(error), _) => Line::from(Span::styled(error.clone(), Style::default().fg(Color::Red))),
+        (None, Source::Replay(_)) => replay::shortcuts_line(),
+        (None, Source::Live { .. }) => panels::shortcuts_line(&[("SPACE", "pause"), (".", "step"), ("+/-", "speed")]),
+    };
+    let mut spans = shortcuts.spans;
+    spans.extend(panels::shortcuts_line(&[("Z/X", "zoom"), ("F", "fit"), ("TAB", "follow"), ("L", "labels"), ("O", "frame"), ("G", "Lagrange"), ("ESC", "quit")]).spans);
+    f.render_widget(Paragraph::new(Line::from(spans)).alignment(Alignment::Center), bottom);
+}
 
-fn setup_terminal() -> Result<Terminal> {
+fn setup_terminal() -> io::Result<Terminal> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
@@ -223,15 +391,12 @@ fn setup_terminal() -> Result<Terminal> {
     Ok(terminal)
 }
 
-enum AppEvent {
+pub enum AppEvent {
     Tick,
     KeyPressed(KeyCode),
-    Resize(Rect),
-    RefreshAufBuffer,
 }
 
 pub struct EventHandler {
-    sender: mpsc::Sender<AppEvent>,
     receiver: mpsc::Receiver<AppEvent>,
     _handler: thread::JoinHandle<()>
 }
@@ -241,7 +406,6 @@ impl EventHandler {
         let (sender, receiver) = mpsc::channel();
 
         let handler = {
-            let sender = sender.clone();
             thread::spawn(move || {
                 let mut last_tick = std::time::Instant::now();
                 loop {
@@ -263,11 +427,7 @@ impl EventHandler {
             })
         };
 
-        Self { sender, receiver, _handler: handler }
-    }
-
-    pub(crate) fn sender(&self) -> mpsc::Sender<AppEvent> {
-        self.sender.clone()
+        Self { receiver, _handler: handler }
     }
 
     fn next(&self) -> std::result::Result<AppEvent, mpsc::RecvError> {
@@ -275,10 +435,7 @@ impl EventHandler {
     }
 
     fn try_next(&self) -> Option<AppEvent> {
-        match self.receiver.try_recv() {
-            Ok(e) => Some(e),
-            Err(_) => None
-        }
+        self.receiver.try_recv().ok()
     }
 
     pub(crate) fn receive_events<F>(&self, mut f: F)
@@ -292,24 +449,7 @@ impl EventHandler {
         match event::read().expect("unable to read event") {
             Event::Key(e) if e.kind == KeyEventKind::Press =>
                 sender.send(AppEvent::KeyPressed(e.code)),
-            Event::Resize(w, h) =>
-                sender.send(AppEvent::Resize(Rect::new(0, 0, w, h))),
             _ => Ok(())
         }.expect("failed to send event")
     }
 }
-
-fn baseline_rects() -> EffectTimelineRects {
-    // giving an approximate layout so that all rects resolve to unique values,
-    // enabling us to get the actual layout from the effect timeline. something
-    // of a hack...
-    EffectTimelineRects {
-        tree: Rect::new(0, 0, 25, 32),
-        chart: Rect::new(35, 0, 65, 32),
-        cell_filter: Rect::new(25, 0, 6, 32),
-        areas: Rect::new(31, 0, 4, 32),
-        legend: Rect::new(35, 34, 29, 6),
-        cell_filter_legend: Rect::new(35, 34, 9, 2),
-        areas_legend: Rect::new(48, 34, 16, 2),
-    }
-}
\ No newline at end of file